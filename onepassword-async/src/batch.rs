@@ -0,0 +1,136 @@
+use crate::{
+    Client,
+    wrappers::{ItemWrapper, VaultWrapper},
+};
+use futures::{StreamExt, stream};
+use onepassword_shared::errors::LookupError;
+use secrecy::SecretString;
+
+/// How many operations a [`Batch`] runs at once unless told otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Operations queued with [`Client::batch`], which run concurrently once executed.
+///
+/// Vaults and items are looked up by id or title like [`Client::vault`] and
+/// [`VaultWrapper::item`], so after the first listing of the vaults the rest reuse their names
+/// as long as they're recent enough.
+pub struct Batch {
+    client: Client,
+    concurrency: usize,
+    operations: Vec<Operation>,
+}
+
+enum Operation {
+    Vaults,
+    Items { vault: String },
+    Item { vault: String, item: String },
+    Resolve { reference: String },
+    Password { item: ItemWrapper },
+}
+
+/// What an operation of a [`Batch`] returned, in the variant matching the method queuing it.
+#[derive(Debug)]
+pub enum BatchOutput {
+    Vaults(Vec<VaultWrapper>),
+    /// The items of the vault, or `None` if there's no such vault.
+    Items(Option<Vec<ItemWrapper>>),
+    Item(Option<ItemWrapper>),
+    Secret(SecretString),
+    Password(Option<SecretString>),
+}
+
+impl Client {
+    /// Starts a batch of operations to run concurrently, see [`Batch`].
+    pub fn batch(&self) -> Batch {
+        Batch {
+            client: self.clone(),
+            concurrency: DEFAULT_CONCURRENCY,
+            operations: Vec::new(),
+        }
+    }
+}
+
+impl Batch {
+    /// Limits how many operations run at once, which is at least one.
+    pub fn concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+
+    pub fn vaults(mut self) -> Self {
+        self.operations.push(Operation::Vaults);
+        self
+    }
+
+    pub fn items(mut self, vault: impl Into<String>) -> Self {
+        self.operations.push(Operation::Items {
+            vault: vault.into(),
+        });
+        self
+    }
+
+    pub fn item(mut self, vault: impl Into<String>, item: impl Into<String>) -> Self {
+        self.operations.push(Operation::Item {
+            vault: vault.into(),
+            item: item.into(),
+        });
+        self
+    }
+
+    pub fn resolve(mut self, reference: impl Into<String>) -> Self {
+        self.operations.push(Operation::Resolve {
+            reference: reference.into(),
+        });
+        self
+    }
+
+    /// Queues [`ItemWrapper::password`] for each of `items`.
+    pub fn passwords(mut self, items: impl IntoIterator<Item = ItemWrapper>) -> Self {
+        self.operations
+            .extend(items.into_iter().map(|item| Operation::Password { item }));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Runs the operations, returning their results in the order they were queued in.
+    pub async fn execute(self) -> Vec<Result<BatchOutput, LookupError>> {
+        let Self {
+            client,
+            concurrency,
+            operations,
+        } = self;
+
+        stream::iter(operations)
+            .map(|operation| operation.run(&client))
+            .buffered(concurrency)
+            .collect()
+            .await
+    }
+}
+
+impl Operation {
+    async fn run(self, client: &Client) -> Result<BatchOutput, LookupError> {
+        let output = match self {
+            Self::Vaults => BatchOutput::Vaults(client.vaults().await?),
+            Self::Items { vault } => match client.vault(&vault).await? {
+                Some(vault) => BatchOutput::Items(Some(vault.items().await?)),
+                None => BatchOutput::Items(None),
+            },
+            Self::Item { vault, item } => match client.vault(&vault).await? {
+                Some(vault) => BatchOutput::Item(vault.item(&item).await?),
+                None => BatchOutput::Item(None),
+            },
+            Self::Resolve { reference } => BatchOutput::Secret(client.resolve(&reference).await?),
+            Self::Password { item } => BatchOutput::Password(item.password().await?),
+        };
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,62 @@
+//! The caching client, see `onepassword_shared::define_caching!`.
+
+onepassword_shared::define_caching!(async);
+
+mod refresher {
+    use super::CachingClient;
+    use crate::Client;
+    use futures::{
+        StreamExt,
+        channel::mpsc::{self, Receiver, Sender},
+    };
+    use onepassword_shared::cache::SecretCache;
+    use std::sync::{Arc, Mutex};
+
+    /// Queues references for the future returned by [`CachingClient::refresher`].
+    pub(super) struct Refresher {
+        sender: Mutex<Sender<String>>,
+        receiver: Mutex<Option<Receiver<String>>>,
+    }
+
+    impl Refresher {
+        pub(super) fn new(_client: &Client, cache: &Arc<SecretCache>) -> Self {
+            let (sender, receiver) = mpsc::channel(cache.config().max_entries);
+
+            Self {
+                sender: Mutex::new(sender),
+                receiver: Mutex::new(Some(receiver)),
+            }
+        }
+
+        pub(super) fn queue(&self, reference: &str) -> bool {
+            self.sender
+                .lock()
+                .unwrap()
+                .try_send(reference.to_owned())
+                .is_ok()
+        }
+    }
+
+    impl CachingClient {
+        /// Returns the future doing the refresh-ahead, which finishes once every clone of the
+        /// caching client is dropped. Only the first call returns a future that does anything.
+        pub fn refresher(&self) -> impl Future<Output = ()> + use<> {
+            let receiver = self.0.refresher.receiver.lock().unwrap().take();
+            let client = self.0.client.clone();
+            let cache = Arc::clone(&self.0.cache);
+
+            async move {
+                let Some(mut receiver) = receiver else {
+                    return;
+                };
+
+                while let Some(reference) = receiver.next().await {
+                    match client.resolve(&reference).await {
+                        Ok(secret) => cache.refreshed(&reference, secret),
+                        Err(_) => cache.refresh_failed(&reference),
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,21 +1,33 @@
-use onepassword_shared::types::{Invocation, InvocationWrapper};
-
+pub mod batch;
+pub mod caching;
+pub mod registry;
 pub mod wrappers;
 
-pub use onepassword_shared::types::ClientConfig;
-pub use onepassword_sys::{Error as FfiError, version};
+pub use batch::{Batch, BatchOutput};
+pub use caching::CachingClient;
+#[cfg(feature = "metrics")]
+pub use onepassword_shared::metrics::MetricsFacade;
+pub use onepassword_shared::{
+    cache::CacheConfig,
+    config::ClientConfigBuilder,
+    errors::{ConfigError, InitError, LookupError, RegistryError},
+    metrics::{CallRecord, MetricsSink},
+    registry::RegistryConfig,
+    response::Response,
+    retry::RetryPolicy,
+    types::{Auth, ClientConfig, ItemView, VaultView, WebsiteView},
+};
+pub use onepassword_sys::{Error as FfiError, ErrorKind, version};
+pub use registry::ClientRegistry;
 pub use wrappers::Client;
 
-pub async fn invoke<T: serde::de::DeserializeOwned>(invocation: Invocation) -> Result<T, FfiError> {
-    let serialized_config = serde_json::to_string(&InvocationWrapper { invocation }).unwrap();
-    let result = onepassword_sys::invoke(&serialized_config).await?;
-    let value = serde_json::from_reader(result.as_ref()).unwrap();
-    Ok(value)
-}
+onepassword_shared::define_invoke!(async);
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use onepassword_shared::types::Invocation;
+    use std::time::Duration;
 
     const ONEPASS_SERVICE_ACCOUNT_TOKEN: &str =
         "https://developer.1password.com/docs/service-accounts/get-started";
@@ -23,9 +35,9 @@ mod tests {
     #[tokio::test]
     async fn connect() {
         let vaults = Client::new(ClientConfig {
-            service_account_token: ONEPASS_SERVICE_ACCOUNT_TOKEN.to_owned(),
-            integration_name: env!("CARGO_PKG_NAME"),
-            integration_version: env!("CARGO_PKG_VERSION"),
+            auth: Auth::ServiceAccount(ONEPASS_SERVICE_ACCOUNT_TOKEN.into()),
+            integration_name: env!("CARGO_PKG_NAME").into(),
+            integration_version: env!("CARGO_PKG_VERSION").into(),
             ..Default::default()
         })
         .await
@@ -36,4 +48,61 @@ mod tests {
 
         eprintln!("{vaults:?}");
     }
+
+    fn assert_send<T: Send + 'static>(_: T) {}
+
+    /// Only has to compile, so the futures can be spawned on a multi-threaded runtime.
+    #[test]
+    fn public_futures_are_send() {
+        let _ = |client: Client,
+                 vault: wrappers::VaultWrapper,
+                 item: wrappers::ItemWrapper,
+                 caching: CachingClient,
+                 registry: std::sync::Arc<ClientRegistry>,
+                 invocation: Invocation| {
+            assert_send(Client::new(ClientConfig::default()));
+            assert_send(invoke::<()>(invocation.clone()));
+            assert_send(invoke_with_timeout::<()>(invocation, None));
+
+            let c = client.clone();
+            assert_send(async move { c.vaults().await });
+            let c = client.clone();
+            assert_send(async move { c.vault_by_title("Private").await });
+            let c = client.clone();
+            assert_send(async move { c.vault("Private").await });
+            let c = client.clone();
+            assert_send(async move { c.vault_by_id("id").await });
+            let c = client.clone();
+            assert_send(async move { c.resolve("op://Private/Login/password").await });
+            let c = client.clone();
+            assert_send(async move { c.search("login").await });
+            let c = client.clone();
+            assert_send(async move { c.ping().await });
+            assert_send(client.batch().vaults().resolve("op://x/y/z").execute());
+            assert_send(client.close());
+
+            assert_send(async move {
+                vault.items().await?;
+                vault.items_iter().await?;
+                vault.item_by_title("Login").await?;
+                vault.item("Login").await?;
+                vault.item_by_id("id").await?;
+                vault.items_for_website("example.com").await?;
+                Ok::<_, LookupError>(())
+            });
+            assert_send(async move { item.password().await });
+
+            let c = caching.clone();
+            assert_send(async move { c.resolve("op://Private/Login/password").await });
+            let c = caching.clone();
+            assert_send(async move { c.resolve_with_ttl("op://x/y/z", Duration::ZERO).await });
+            assert_send(caching.refresher());
+
+            let r = registry.clone();
+            assert_send(async move { r.client("prod").await });
+            let r = registry.clone();
+            assert_send(async move { r.resolve("prod:op://Private/Login/password").await });
+            assert_send(async move { registry.close().await });
+        };
+    }
 }
@@ -0,0 +1,3 @@
+//! The client registry, see `onepassword_shared::define_registry!`.
+
+onepassword_shared::define_registry!(async);
@@ -0,0 +1,290 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use secrecy::SecretString;
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// How long a resolved secret is served from the cache, unless overridden per reference.
+    pub ttl: Duration,
+    /// How many secrets are kept at most, the least recently used one is evicted first.
+    pub max_entries: usize,
+    /// Secrets used within this long of expiring are refreshed in the background, so callers
+    /// keep getting cached values instead of waiting on the SDK.
+    ///
+    /// The window is capped at half of each secret's TTL, so a secret that was just cached or
+    /// refreshed is never due for refreshing again right away.
+    pub refresh_ahead: Option<Duration>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(5 * 60),
+            max_entries: 1024,
+            refresh_ahead: None,
+        }
+    }
+}
+
+pub enum CacheLookup {
+    Hit(SecretString),
+    /// The secret is still valid but about to expire, and the caller should refresh it.
+    RefreshDue(SecretString),
+    Miss,
+}
+
+struct Entry {
+    secret: SecretString,
+    ttl: Duration,
+    expires_at: Instant,
+    last_used: Instant,
+    refreshing: bool,
+}
+
+/// Resolved secrets keyed by their secret reference.
+///
+/// Secrets stay wrapped in [`SecretString`], so they are zeroized whenever they are evicted,
+/// invalidated or replaced.
+pub struct SecretCache {
+    config: CacheConfig,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl SecretCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    pub fn get(&self, reference: &str) -> CacheLookup {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        let Some(entry) = entries.get_mut(reference) else {
+            return CacheLookup::Miss;
+        };
+
+        if entry.expires_at <= now {
+            entries.remove(reference);
+            return CacheLookup::Miss;
+        }
+
+        entry.last_used = now;
+
+        let refresh_due = self
+            .config
+            .refresh_ahead
+            .is_some_and(|window| entry.expires_at - now <= window.min(entry.ttl / 2));
+
+        if refresh_due && !entry.refreshing {
+            entry.refreshing = true;
+            CacheLookup::RefreshDue(entry.secret.clone())
+        } else {
+            CacheLookup::Hit(entry.secret.clone())
+        }
+    }
+
+    /// Caches `secret` for `ttl`, or if that's `None`, for the TTL the reference was cached with
+    /// before (falling back to the configured one).
+    pub fn insert(&self, reference: &str, secret: SecretString, ttl: Option<Duration>) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        let ttl = ttl
+            .or_else(|| entries.get(reference).map(|entry| entry.ttl))
+            .unwrap_or(self.config.ttl);
+
+        if !entries.contains_key(reference) {
+            entries.retain(|_, entry| entry.expires_at > now);
+
+            if entries.len() >= self.config.max_entries {
+                let least_recently_used = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(reference, _)| reference.clone());
+
+                if let Some(reference) = least_recently_used {
+                    entries.remove(&reference);
+                }
+            }
+        }
+
+        if self.config.max_entries == 0 {
+            return;
+        }
+
+        entries.insert(
+            reference.to_owned(),
+            Entry {
+                secret,
+                ttl,
+                expires_at: now + ttl,
+                last_used: now,
+                refreshing: false,
+            },
+        );
+    }
+
+    /// Replaces the secret of a reference handed out for refreshing with the refreshed one,
+    /// keeping its TTL. Does nothing if the entry was invalidated, evicted or replaced since, so a
+    /// refresh that was already in flight can't bring an invalidated secret back.
+    pub fn refreshed(&self, reference: &str, secret: SecretString) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some(entry) = entries.get_mut(reference)
+            && entry.refreshing
+        {
+            entry.secret = secret;
+            entry.expires_at = now + entry.ttl;
+            entry.refreshing = false;
+        }
+    }
+
+    /// Lets a reference that failed to refresh be handed out for refreshing again.
+    pub fn refresh_failed(&self, reference: &str) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(reference) {
+            entry.refreshing = false;
+        }
+    }
+
+    pub fn invalidate(&self, reference: &str) {
+        self.entries.lock().unwrap().remove(reference);
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+
+    /// Moves the expiry of `reference` closer by `by`, as if it had been cached that long ago.
+    fn age(cache: &SecretCache, reference: &str, by: Duration) {
+        cache
+            .entries
+            .lock()
+            .unwrap()
+            .get_mut(reference)
+            .unwrap()
+            .expires_at -= by;
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = SecretCache::new(CacheConfig {
+            max_entries: 2,
+            ..Default::default()
+        });
+
+        cache.insert("op://a", "a".into(), None);
+        cache.insert("op://b", "b".into(), None);
+        assert!(matches!(cache.get("op://a"), CacheLookup::Hit(_)));
+
+        cache.insert("op://c", "c".into(), None);
+        assert!(matches!(cache.get("op://b"), CacheLookup::Miss));
+        assert!(matches!(cache.get("op://a"), CacheLookup::Hit(_)));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn refreshes_ahead_once() {
+        let cache = SecretCache::new(CacheConfig {
+            refresh_ahead: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+
+        cache.insert("op://a", "a".into(), Some(Duration::from_secs(90)));
+        assert!(matches!(cache.get("op://a"), CacheLookup::Hit(_)));
+
+        age(&cache, "op://a", Duration::from_secs(50));
+        let CacheLookup::RefreshDue(secret) = cache.get("op://a") else {
+            panic!("expected a refresh to be due");
+        };
+        assert_eq!(secret.expose_secret(), "a");
+        assert!(matches!(cache.get("op://a"), CacheLookup::Hit(_)));
+
+        cache.refresh_failed("op://a");
+        assert!(matches!(cache.get("op://a"), CacheLookup::RefreshDue(_)));
+    }
+
+    #[test]
+    fn invalidated_entries_stay_invalidated_after_refreshing() {
+        let cache = SecretCache::new(CacheConfig {
+            refresh_ahead: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+
+        cache.insert("op://a", "a".into(), Some(Duration::from_secs(90)));
+        age(&cache, "op://a", Duration::from_secs(50));
+        assert!(matches!(cache.get("op://a"), CacheLookup::RefreshDue(_)));
+
+        cache.invalidate("op://a");
+        cache.refreshed("op://a", "stale".into());
+        assert!(matches!(cache.get("op://a"), CacheLookup::Miss));
+
+        cache.insert("op://a", "b".into(), Some(Duration::from_secs(90)));
+        cache.refreshed("op://a", "stale".into());
+        age(&cache, "op://a", Duration::from_secs(50));
+        let CacheLookup::RefreshDue(secret) = cache.get("op://a") else {
+            panic!("expected a refresh to be due");
+        };
+        assert_eq!(secret.expose_secret(), "b");
+
+        cache.refreshed("op://a", "c".into());
+        let CacheLookup::Hit(secret) = cache.get("op://a") else {
+            panic!("expected a fresh secret");
+        };
+        assert_eq!(secret.expose_secret(), "c");
+    }
+
+    #[test]
+    fn refresh_window_stays_shorter_than_the_ttl() {
+        let cache = SecretCache::new(CacheConfig {
+            ttl: Duration::from_secs(30),
+            refresh_ahead: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+
+        cache.insert("op://a", "a".into(), None);
+        cache.insert("op://b", "b".into(), Some(Duration::from_secs(10)));
+        assert!(matches!(cache.get("op://a"), CacheLookup::Hit(_)));
+        assert!(matches!(cache.get("op://b"), CacheLookup::Hit(_)));
+
+        age(&cache, "op://a", Duration::from_secs(15));
+        assert!(matches!(cache.get("op://a"), CacheLookup::RefreshDue(_)));
+
+        cache.refreshed("op://a", "a".into());
+        assert!(matches!(cache.get("op://a"), CacheLookup::Hit(_)));
+    }
+
+    #[test]
+    fn expired_entries_are_dropped() {
+        let cache = SecretCache::new(CacheConfig::default());
+
+        cache.insert("op://a", "a".into(), Some(Duration::ZERO));
+        assert!(matches!(cache.get("op://a"), CacheLookup::Miss));
+        assert!(cache.is_empty());
+    }
+}
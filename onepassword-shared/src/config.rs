@@ -0,0 +1,353 @@
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+    time::Duration,
+};
+
+use secrecy::{SecretString, zeroize::Zeroizing};
+
+use crate::{
+    errors::ConfigError,
+    metrics::MetricsSink,
+    retry::RetryPolicy,
+    types::{Auth, ClientConfig},
+};
+
+enum AuthSource {
+    Token(Zeroizing<String>),
+    TokenEnv(String),
+    TokenFile(PathBuf),
+    TokenCommand(Command),
+    DesktopApp(String),
+}
+
+/// Builds a [`ClientConfig`], reading the service account token from wherever it's kept.
+///
+/// The token is only read in [`ClientConfigBuilder::build`], and if several token sources or
+/// [`ClientConfigBuilder::desktop_app`] are set the last one wins.
+pub struct ClientConfigBuilder {
+    config: ClientConfig,
+    auth: Option<AuthSource>,
+}
+
+impl ClientConfig {
+    pub fn builder() -> ClientConfigBuilder {
+        ClientConfigBuilder {
+            config: ClientConfig::default(),
+            auth: None,
+        }
+    }
+}
+
+impl ClientConfigBuilder {
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.auth = Some(AuthSource::Token(Zeroizing::new(token.into())));
+        self
+    }
+
+    /// Reads the token from an environment variable, e.g. `OP_SERVICE_ACCOUNT_TOKEN`.
+    pub fn token_from_env(mut self, variable: impl Into<String>) -> Self {
+        self.auth = Some(AuthSource::TokenEnv(variable.into()));
+        self
+    }
+
+    /// Reads the token from a file, which on Unix mustn't be accessible by group or others.
+    pub fn token_from_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.auth = Some(AuthSource::TokenFile(path.into()));
+        self
+    }
+
+    /// Uses what `command` prints to stdout as the token, e.g. to fetch it from a secret store.
+    pub fn token_from_command(mut self, command: Command) -> Self {
+        self.auth = Some(AuthSource::TokenCommand(command));
+        self
+    }
+
+    /// Signs in through the desktop app instead of with a service account, see
+    /// [`Auth::DesktopApp`].
+    pub fn desktop_app(mut self, account_name: impl Into<String>) -> Self {
+        self.auth = Some(AuthSource::DesktopApp(account_name.into()));
+        self
+    }
+
+    /// Overrides where the desktop app's integration socket is expected.
+    pub fn desktop_app_socket(mut self, socket: impl Into<PathBuf>) -> Self {
+        self.config.desktop_app_socket = Some(socket.into());
+        self
+    }
+
+    pub fn integration(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        version: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.config.integration_name = name.into();
+        self.config.integration_version = version.into();
+        self
+    }
+
+    pub fn os(mut self, os: impl Into<Cow<'static, str>>) -> Self {
+        self.config.os = os.into();
+        self
+    }
+
+    pub fn os_version(mut self, os_version: impl Into<Cow<'static, str>>) -> Self {
+        self.config.os_version = os_version.into();
+        self
+    }
+
+    pub fn architecture(mut self, architecture: impl Into<Cow<'static, str>>) -> Self {
+        self.config.architecture = architecture.into();
+        self
+    }
+
+    pub fn name_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.config.name_cache_ttl = ttl;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.config.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    pub fn metrics(mut self, metrics: Arc<dyn MetricsSink>) -> Self {
+        self.config.metrics = Some(metrics);
+        self
+    }
+
+    pub fn build(self) -> Result<ClientConfig, ConfigError> {
+        let token = match self.auth.ok_or(ConfigError::MissingToken)? {
+            AuthSource::Token(token) => token,
+            AuthSource::TokenEnv(variable) => read_token_env(variable)?,
+            AuthSource::TokenFile(path) => read_token_file(&path)?,
+            AuthSource::TokenCommand(command) => run_token_command(command)?,
+            AuthSource::DesktopApp(account_name) => {
+                if account_name.trim().is_empty() {
+                    return Err(ConfigError::EmptyAccountName);
+                }
+                return Ok(ClientConfig {
+                    auth: Auth::DesktopApp { account_name },
+                    ..self.config
+                });
+            }
+        };
+
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(ConfigError::EmptyToken);
+        }
+
+        Ok(ClientConfig {
+            auth: Auth::ServiceAccount(SecretString::from(token)),
+            ..self.config
+        })
+    }
+}
+
+fn read_token_env(variable: String) -> Result<Zeroizing<String>, ConfigError> {
+    match std::env::var_os(&variable) {
+        Some(token) => zeroizing_utf8(Zeroizing::new(token.into_encoded_bytes()))
+            .ok_or(ConfigError::EnvVarNotUnicode { variable }),
+        None => Err(ConfigError::MissingEnvVar { variable }),
+    }
+}
+
+fn read_token_file(path: &Path) -> Result<Zeroizing<String>, ConfigError> {
+    let io_error = |source| ConfigError::ReadFile {
+        path: path.to_owned(),
+        source,
+    };
+
+    // Checked on the opened file rather than the path, so it can't be swapped in between.
+    let file = fs::File::open(path).map_err(io_error)?;
+    let metadata = file.metadata().map_err(io_error)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = metadata.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(ConfigError::InsecureFile {
+                path: path.to_owned(),
+                mode: mode & 0o777,
+            });
+        }
+    }
+
+    // Sized up front and read no further, so the token is never left behind by a reallocation.
+    let len = metadata.len();
+    let mut token = Zeroizing::new(Vec::with_capacity(len as usize));
+    file.take(len).read_to_end(&mut token).map_err(io_error)?;
+
+    zeroizing_utf8(token).ok_or_else(|| {
+        io_error(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the token isn't valid UTF-8",
+        ))
+    })
+}
+
+fn run_token_command(mut command: Command) -> Result<Zeroizing<String>, ConfigError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+
+    let output = command.output().map_err(|source| ConfigError::Command {
+        program: program.clone(),
+        source,
+    })?;
+    let stdout = Zeroizing::new(output.stdout);
+
+    if !output.status.success() {
+        return Err(ConfigError::CommandFailed {
+            program,
+            status: output.status,
+        });
+    }
+
+    zeroizing_utf8(stdout).ok_or(ConfigError::CommandOutputNotUtf8 { program })
+}
+
+/// Turns `bytes` into a string without copying them, or wipes them if they aren't UTF-8.
+fn zeroizing_utf8(mut bytes: Zeroizing<Vec<u8>>) -> Option<Zeroizing<String>> {
+    match String::from_utf8(std::mem::take(&mut *bytes)) {
+        Ok(string) => Some(Zeroizing::new(string)),
+        Err(error) => {
+            *bytes = error.into_bytes();
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+
+    fn token(config: &ClientConfig) -> &str {
+        match &config.auth {
+            Auth::ServiceAccount(token) => token.expose_secret(),
+            Auth::DesktopApp { .. } => panic!("expected a service account"),
+        }
+    }
+
+    #[test]
+    fn requires_a_token() {
+        assert!(matches!(
+            ClientConfig::builder().build(),
+            Err(ConfigError::MissingToken)
+        ));
+        assert!(matches!(
+            ClientConfig::builder().token(" \n").build(),
+            Err(ConfigError::EmptyToken)
+        ));
+        assert!(matches!(
+            ClientConfig::builder().desktop_app("").build(),
+            Err(ConfigError::EmptyAccountName)
+        ));
+    }
+
+    #[test]
+    fn only_exposes_the_token_to_the_sdk() {
+        let config = ClientConfig::builder().token("ops_secret").build().unwrap();
+
+        assert!(!format!("{config:?}").contains("ops_secret"));
+
+        let json = config.to_json();
+        assert!(json.capacity() == json.len());
+        assert!(
+            std::str::from_utf8(&json)
+                .unwrap()
+                .contains(r#""serviceAccountToken":"ops_secret""#)
+        );
+    }
+
+    #[test]
+    fn signs_in_through_the_desktop_app() {
+        let config = ClientConfig::builder()
+            .token("ops_secret")
+            .desktop_app("my.1password.com")
+            .build()
+            .unwrap();
+
+        let json = config.to_json();
+        let json = std::str::from_utf8(&json).unwrap();
+        assert!(json.contains(r#""accountName":"my.1password.com""#));
+        assert!(!json.contains("serviceAccountToken"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_tokens_from_files_and_commands() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("op-token-{}", std::process::id()));
+        fs::write(&path, "ops_token\n").unwrap();
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let insecure = ClientConfig::builder().token_from_file(&path).build();
+        assert!(matches!(
+            insecure,
+            Err(ConfigError::InsecureFile { mode: 0o644, .. })
+        ));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let config = ClientConfig::builder()
+            .token_from_file(&path)
+            .build()
+            .unwrap();
+        assert_eq!(token(&config), "ops_token");
+        fs::remove_file(&path).unwrap();
+
+        let mut command = Command::new("echo");
+        command.arg("ops_from_command");
+        let config = ClientConfig::builder()
+            .token_from_command(command)
+            .build()
+            .unwrap();
+        assert_eq!(token(&config), "ops_from_command");
+
+        let failing = ClientConfig::builder()
+            .token_from_command(Command::new("false"))
+            .build();
+        assert!(matches!(failing, Err(ConfigError::CommandFailed { .. })));
+
+        let mut command = Command::new("printf");
+        command.arg(r"ops_\377");
+        let not_utf8 = ClientConfig::builder().token_from_command(command).build();
+        assert!(matches!(
+            not_utf8,
+            Err(ConfigError::CommandOutputNotUtf8 { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_invalid_tokens_out_of_errors() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let variable = format!("OP_TEST_TOKEN_{}", std::process::id());
+        // SAFETY: No other test reads or writes this variable.
+        unsafe { std::env::set_var(&variable, std::ffi::OsStr::from_bytes(b"ops_\xff")) };
+
+        let error = ClientConfig::builder()
+            .token_from_env(&variable)
+            .build()
+            .unwrap_err();
+        assert!(matches!(error, ConfigError::EnvVarNotUnicode { .. }));
+        assert!(!format!("{error} {error:?}").contains("ops_"));
+
+        unsafe { std::env::remove_var(&variable) };
+        let missing = ClientConfig::builder().token_from_env(&variable).build();
+        assert!(matches!(missing, Err(ConfigError::MissingEnvVar { .. })));
+    }
+}
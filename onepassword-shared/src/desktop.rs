@@ -0,0 +1,65 @@
+//! Checks for the desktop app integration used by [`Auth::DesktopApp`].
+//!
+//! [`Auth::DesktopApp`]: crate::types::Auth::DesktopApp
+
+use std::path::Path;
+
+use crate::{
+    errors::InitError,
+    types::{Auth, ClientConfig},
+};
+
+/// Checks that the desktop app can be reached if `config` authenticates through it and its
+/// socket was given explicitly. Otherwise, finding the app is left to the SDK.
+pub fn check(config: &ClientConfig) -> Result<(), InitError> {
+    match (&config.auth, &config.desktop_app_socket) {
+        (Auth::DesktopApp { .. }, Some(socket)) => ensure_available(socket),
+        _ => Ok(()),
+    }
+}
+
+/// Fails with [`InitError::DesktopAppUnavailable`] if nothing accepts connections on `socket`,
+/// so that a missing app is reported clearly rather than as an opaque SDK error.
+pub fn ensure_available(socket: &Path) -> Result<(), InitError> {
+    #[cfg(unix)]
+    std::os::unix::net::UnixStream::connect(socket).map_err(|source| {
+        InitError::DesktopAppUnavailable {
+            socket: socket.to_owned(),
+            source,
+        }
+    })?;
+    #[cfg(not(unix))]
+    let _ = socket;
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn checks_the_socket_is_listening() {
+        let socket = std::env::temp_dir().join(format!("op-desktop-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+
+        let mut config = ClientConfig::builder()
+            .desktop_app("my.1password.com")
+            .build()
+            .unwrap();
+        assert!(check(&config).is_ok());
+
+        config.desktop_app_socket = Some(socket.clone());
+        assert!(matches!(
+            check(&config),
+            Err(InitError::DesktopAppUnavailable { .. })
+        ));
+
+        let listener = UnixListener::bind(&socket).unwrap();
+        assert!(check(&config).is_ok());
+
+        drop(listener);
+        std::fs::remove_file(&socket).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+use core::fmt::Display;
+use std::{io, path::PathBuf, process::ExitStatus};
+
+use onepassword_sys::Error as FfiError;
+
+#[derive(Debug)]
+pub enum LookupError {
+    /// More than one object matched, so there is no telling which one was meant.
+    Ambiguous {
+        ids: Vec<String>,
+    },
+    Ffi(FfiError),
+}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Ambiguous { ids } => {
+                write!(f, "ambiguous lookup, matching ids: {}", ids.join(", "))
+            }
+            Self::Ffi(error) => write!(f, "{error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for LookupError {}
+
+impl From<FfiError> for LookupError {
+    fn from(value: FfiError) -> Self {
+        Self::Ffi(value)
+    }
+}
+
+/// Why a client couldn't be created.
+#[derive(Debug)]
+pub enum InitError {
+    /// Nothing is listening on the desktop app's integration socket, e.g. because the app isn't
+    /// running or the integration is turned off in its developer settings.
+    DesktopAppUnavailable {
+        socket: PathBuf,
+        source: io::Error,
+    },
+    /// The SDK couldn't sign in through the desktop app, e.g. because it isn't running, its
+    /// integration is turned off or the user didn't approve the client.
+    DesktopApp(FfiError),
+    Ffi(FfiError),
+}
+
+impl Display for InitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::DesktopAppUnavailable { socket, source } => write!(
+                f,
+                "the 1Password desktop app integration isn't available at {}: {source}",
+                socket.display()
+            ),
+            Self::DesktopApp(error) => write!(
+                f,
+                "couldn't sign in through the 1Password desktop app: {}",
+                error.message()
+            ),
+            Self::Ffi(error) => write!(f, "{error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for InitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::DesktopAppUnavailable { source, .. } => Some(source),
+            Self::DesktopApp(_) | Self::Ffi(_) => None,
+        }
+    }
+}
+
+impl From<FfiError> for InitError {
+    fn from(value: FfiError) -> Self {
+        Self::Ffi(value)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Neither a way to get the service account token nor a desktop app account was given.
+    MissingToken,
+    /// The token source gave an empty token.
+    EmptyToken,
+    /// The desktop app account name is empty.
+    EmptyAccountName,
+    MissingEnvVar {
+        variable: String,
+    },
+    /// The variable isn't valid Unicode. What it holds isn't kept, since it's likely the token.
+    EnvVarNotUnicode {
+        variable: String,
+    },
+    ReadFile {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// The token file can be accessed by users other than its owner.
+    InsecureFile {
+        path: PathBuf,
+        mode: u32,
+    },
+    /// The token command couldn't be run.
+    Command {
+        program: String,
+        source: io::Error,
+    },
+    CommandFailed {
+        program: String,
+        status: ExitStatus,
+    },
+    CommandOutputNotUtf8 {
+        program: String,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MissingToken => f.write_str(
+                "neither a service account token nor a desktop app account was configured",
+            ),
+            Self::EmptyToken => f.write_str("the service account token is empty"),
+            Self::EmptyAccountName => f.write_str("the desktop app account name is empty"),
+            Self::MissingEnvVar { variable } => {
+                write!(f, "couldn't read the token from ${variable}: it isn't set")
+            }
+            Self::EnvVarNotUnicode { variable } => {
+                write!(
+                    f,
+                    "couldn't read the token from ${variable}: it isn't valid Unicode"
+                )
+            }
+            Self::ReadFile { path, source } => {
+                write!(
+                    f,
+                    "couldn't read the token from {}: {source}",
+                    path.display()
+                )
+            }
+            Self::InsecureFile { path, mode } => write!(
+                f,
+                "the token file {} has mode {mode:o}, but must not be accessible by group or others",
+                path.display()
+            ),
+            Self::Command { program, source } => {
+                write!(f, "couldn't run token command `{program}`: {source}")
+            }
+            Self::CommandFailed { program, status } => {
+                write!(f, "token command `{program}` failed with {status}")
+            }
+            Self::CommandOutputNotUtf8 { program } => {
+                write!(f, "token command `{program}` printed invalid UTF-8")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ReadFile { source, .. } | Self::Command { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RegistryError {
+    ReadFile {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// An account was referred to that isn't in the registry's config.
+    UnknownAccount {
+        account: String,
+    },
+    /// The reference has no account prefix, matches no route and there's no default account.
+    NoAccount {
+        reference: String,
+    },
+    Config {
+        account: String,
+        source: ConfigError,
+    },
+    Init {
+        account: String,
+        source: InitError,
+    },
+    Lookup {
+        account: String,
+        source: LookupError,
+    },
+    /// Closing the clients failed for these accounts.
+    Close {
+        errors: Vec<(String, FfiError)>,
+    },
+}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ReadFile { path, source } => {
+                write!(f, "couldn't read {}: {source}", path.display())
+            }
+            Self::Parse { path, source } => {
+                write!(f, "invalid config {}: {source}", path.display())
+            }
+            Self::UnknownAccount { account } => write!(f, "unknown account `{account}`"),
+            Self::NoAccount { reference } => write!(f, "no account to resolve {reference} with"),
+            Self::Config { account, source } => write!(f, "account `{account}`: {source}"),
+            Self::Init { account, source } => write!(f, "account `{account}`: {source}"),
+            Self::Lookup { account, source } => write!(f, "account `{account}`: {source}"),
+            Self::Close { errors } => {
+                f.write_str("couldn't close the clients of")?;
+                for (account, error) in errors {
+                    write!(f, " `{account}` ({error:?})")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ReadFile { source, .. } => Some(source),
+            Self::Parse { source, .. } => Some(source),
+            Self::Config { source, .. } => Some(source),
+            Self::Init { source, .. } => Some(source),
+            Self::Lookup { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the only element of `matches`, `None` if it's empty, or [`LookupError::Ambiguous`]
+/// with the ids of every match if there are several.
+pub fn single_match<T>(
+    matches: impl IntoIterator<Item = T>,
+    id: impl Fn(&T) -> &str,
+) -> Result<Option<T>, LookupError> {
+    let mut matches: Vec<T> = matches.into_iter().collect();
+
+    match matches.len() {
+        0 => Ok(None),
+        1 => Ok(matches.pop()),
+        _ => Err(LookupError::Ambiguous {
+            ids: matches.iter().map(|m| id(m).to_owned()).collect(),
+        }),
+    }
+}
@@ -1 +1,20 @@
+pub mod cache;
+pub mod config;
+pub mod desktop;
+pub mod errors;
+mod macros;
+pub mod metrics;
+pub mod platform;
+pub mod registry;
+pub mod resolver;
+pub mod response;
+pub mod retry;
+pub mod search;
+pub mod secret;
+pub mod stream;
+#[cfg(feature = "tracing")]
+pub mod trace;
 pub mod types;
+
+#[doc(hidden)]
+pub use macros::__private;
@@ -0,0 +1,1138 @@
+//! Macros generating the clients of `onepassword` and `onepassword-async` from one definition.
+//!
+//! Every function that calls into the SDK is written with `$($async)?` in front of `fn`, and
+//! `$(.$await)?` after each call to another such function. Expanding a definition with `sync`
+//! drops both to give blocking functions, while `async` keeps them. The few other differences,
+//! like how to sleep or lock a mutex, are handled by [`maybe_async!`](crate::maybe_async).
+//!
+//! The macros are only exported for those two crates, and aren't public API. Their expansions
+//! reach other crates through [`__private`], so the expanding crate has to meet just this:
+//!
+//! - [`define_invoke!`](crate::define_invoke) is expanded at its root, defining the
+//!   `crate::invoke_raw` the other macros call.
+//! - [`define_wrappers!`](crate::define_wrappers) is expanded in `crate::wrappers`, and its
+//!   `Client` is re-exported as `crate::Client`.
+//! - [`define_caching!`](crate::define_caching) is expanded in a module with a `refresher`
+//!   submodule, see there.
+//! - Its `tracing` feature enables `onepassword-shared/tracing`, since the `cfg`s of the expansion
+//!   are checked against the expanding crate's features. Expanding with `async` needs the
+//!   `async` feature of `onepassword-shared`.
+
+/// What the expansions use from other crates, so the expanding crate doesn't have to depend on
+/// the same versions of them.
+pub mod __private {
+    pub use ::onepassword_sys;
+    pub use ::secrecy;
+    pub use ::serde;
+    pub use ::serde_json;
+    #[cfg(feature = "tracing")]
+    pub use ::tracing;
+    #[cfg(feature = "async")]
+    pub use {::futures, ::futures_timer};
+
+    #[cfg(feature = "async")]
+    use onepassword_sys::Error as FfiError;
+
+    /// Fails with [`FfiError::Timeout`] if `future` doesn't finish within `timeout`.
+    ///
+    /// The future is dropped on expiry, which for an SDK call cancels it on the SDK's side.
+    #[cfg(feature = "async")]
+    pub async fn with_timeout<T>(
+        future: impl Future<Output = Result<T, FfiError>>,
+        timeout: Option<core::time::Duration>,
+    ) -> Result<T, FfiError> {
+        use futures::future::{Either, select};
+
+        let Some(timeout) = timeout else {
+            return future.await;
+        };
+
+        match select(core::pin::pin!(future), futures_timer::Delay::new(timeout)).await {
+            Either::Left((output, _)) => output,
+            Either::Right(_) => Err(FfiError::Timeout { after: timeout }),
+        }
+    }
+}
+
+/// Expands to the sync or async version of an operation whose implementations differ, selected
+/// by whether `[await]` or `[]` is passed first.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! maybe_async {
+    ([] Mutex($t:ty)) => { ::std::sync::Mutex<$t> };
+    ([await] Mutex($t:ty)) => { $crate::__private::futures::lock::Mutex<$t> };
+    ([] Mutex::new($value:expr)) => { ::std::sync::Mutex::new($value) };
+    ([await] Mutex::new($value:expr)) => { $crate::__private::futures::lock::Mutex::new($value) };
+
+    ([] lock($mutex:expr)) => { $mutex.lock().unwrap() };
+    ([await] lock($mutex:expr)) => { $mutex.lock().await };
+
+    ([] sleep($duration:expr)) => { ::std::thread::sleep($duration) };
+    ([await] sleep($duration:expr)) => {
+        $crate::__private::futures_timer::Delay::new($duration).await
+    };
+
+    ([] invoke($payload:expr, $timeout:expr)) => {
+        $crate::__private::onepassword_sys::invoke_sync($payload, $timeout)
+    };
+    ([await] invoke($payload:expr, $timeout:expr)) => {
+        $crate::__private::with_timeout(
+            $crate::__private::onepassword_sys::invoke($payload),
+            $timeout,
+        )
+        .await
+    };
+
+    ([] client_id_buffer($config:expr, $timeout:expr)) => {
+        $crate::__private::onepassword_sys::get_client_id_buffer_sync($config, $timeout)
+    };
+    ([await] client_id_buffer($config:expr, $timeout:expr)) => {
+        $crate::__private::with_timeout(
+            $crate::__private::onepassword_sys::get_client_id_buffer($config),
+            $timeout,
+        )
+        .await
+    };
+
+    // Runs the call inside `span`, which an async call has to enter every time it's polled.
+    ([] in_span($span:expr, $call:expr)) => { $span.in_scope(|| $call) };
+    ([await] in_span($span:expr, $call:expr)) => {
+        $crate::__private::tracing::Instrument::instrument($call, $span.clone()).await
+    };
+
+    // Collects the results of the calls in order. The async version makes up to `$limit` of
+    // them at once.
+    ([] try_buffered($calls:expr, $limit:expr)) => { $calls.collect::<Result<Vec<_>, _>>() };
+    ([await] try_buffered($calls:expr, $limit:expr)) => {{
+        use $crate::__private::futures::{StreamExt, TryStreamExt};
+
+        $crate::__private::futures::stream::iter($calls)
+            .buffered($limit)
+            .try_collect::<Vec<_>>()
+            .await
+    }};
+}
+
+/// Defines `invoke`, `invoke_with_timeout` and the `invoke_raw` the other macros call, with
+/// either blocking (`sync`) or async (`async`) functions.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! define_invoke {
+    (sync) => {
+        $crate::define_invoke!(@define [] []);
+    };
+    (async) => {
+        $crate::define_invoke!(@define [async] [await]);
+    };
+    (@define [$($async:tt)?] [$($await:tt)?]) => {
+        pub $($async)? fn invoke<T: $crate::__private::serde::de::DeserializeOwned>(
+            invocation: $crate::types::Invocation,
+        ) -> Result<T, $crate::__private::onepassword_sys::Error> {
+            invoke_with_timeout(invocation, None)$(.$await)?
+        }
+
+        /// Like [`invoke`], but fails with [`FfiError::Timeout`] if the call takes longer than
+        /// `timeout`.
+        pub $($async)? fn invoke_with_timeout<T: $crate::__private::serde::de::DeserializeOwned>(
+            invocation: $crate::types::Invocation,
+            timeout: Option<::std::time::Duration>,
+        ) -> Result<T, $crate::__private::onepassword_sys::Error> {
+            let result = invoke_raw(invocation, timeout)$(.$await)? ?;
+
+            let value = $crate::__private::serde_json::from_reader(result.as_ref()).unwrap();
+            Ok(value)
+        }
+
+        /// Makes the invocation, returning the undecoded response.
+        pub(crate) $($async)? fn invoke_raw(
+            invocation: $crate::types::Invocation,
+            timeout: Option<::std::time::Duration>,
+        ) -> Result<
+            $crate::__private::onepassword_sys::RustBuffer,
+            $crate::__private::onepassword_sys::Error,
+        > {
+            let payload = $crate::types::InvocationWrapper { invocation }.to_buffer();
+            #[cfg(feature = "tracing")]
+            $crate::__private::tracing::Span::current().record("request_bytes", payload.len);
+
+            let result = $crate::maybe_async!([$($await)?] invoke(payload, timeout))?;
+            #[cfg(feature = "tracing")]
+            $crate::__private::tracing::Span::current().record("response_bytes", result.len);
+
+            Ok(result)
+        }
+    };
+}
+
+/// Defines `Client`, `ClientInner`, `VaultWrapper` and `ItemWrapper`, with either blocking
+/// (`sync`) or async (`async`) methods.
+#[doc(hidden)]
+#[macro_export]
+#[expect(
+    clippy::crate_in_macro_def,
+    reason = "`invoke_raw` is the expanding crate's"
+)]
+macro_rules! define_wrappers {
+    (sync) => {
+        $crate::define_wrappers!(@define [] []);
+    };
+    (async) => {
+        $crate::define_wrappers!(@define [async] [await]);
+    };
+    (@define [$($async:tt)?] [$($await:tt)?]) => {
+        use crate::invoke_raw;
+        #[cfg(feature = "tracing")]
+        use $crate::trace;
+        use $crate::{
+            desktop,
+            errors::{InitError, LookupError, single_match},
+            metrics::{CallRecord, MetricsSink},
+            resolver::{NameCache, Named, SecretReference, find_by_name, is_id},
+            response::Response,
+            search, secret,
+            stream::JsonArray,
+            types::{Auth, ClientConfig, Invocation, InvocationParameters, Item, Vault},
+        };
+        #[cfg(feature = "tracing")]
+        use $crate::__private::tracing;
+        use $crate::__private::{
+            onepassword_sys::{self, Error as FfiError, RustBuffer},
+            secrecy::SecretString,
+            serde::de::{DeserializeOwned, IgnoredAny},
+            serde_json,
+        };
+        use std::{
+            ops::Deref,
+            sync::{
+                Arc,
+                atomic::{AtomicBool, AtomicU64, Ordering},
+            },
+            time::{Duration, Instant},
+        };
+
+        type FfiResult<T> = Result<T, FfiError>;
+
+        #[derive(Clone)]
+        pub struct Client {
+            inner: Arc<ClientInner>,
+            timeout: Option<Duration>,
+        }
+
+        impl Deref for Client {
+            type Target = ClientInner;
+
+            fn deref(&self) -> &Self::Target {
+                &self.inner
+            }
+        }
+        pub struct ClientInner {
+            id: AtomicU64,
+            closed: AtomicBool,
+            /// Held while the client is created again or closed.
+            lifecycle: $crate::maybe_async!([$($await)?] Mutex(())),
+            config: ClientConfig,
+            names: NameCache,
+        }
+
+        impl ClientInner {
+            fn id(&self) -> u64 {
+                self.id.load(Ordering::Acquire)
+            }
+        }
+
+        impl Drop for ClientInner {
+            fn drop(&mut self) {
+                if !*self.closed.get_mut() {
+                    // There's nobody to report the error to, which is what `Client::close` is for.
+                    let _ = onepassword_sys::free_client(&self.id.get_mut().to_string());
+                }
+            }
+        }
+
+        impl Client {
+            /// Creates a client, failing early with [`InitError::DesktopAppUnavailable`] if it
+            /// should sign in through a desktop app whose configured socket can't be reached.
+            /// Errors the SDK reports while signing in through the desktop app are returned as
+            /// [`InitError::DesktopApp`].
+            pub $($async)? fn new(config: ClientConfig) -> Result<Client, InitError> {
+                desktop::check(&config)?;
+                let desktop_app = matches!(config.auth, Auth::DesktopApp { .. });
+
+                #[cfg(feature = "tracing")]
+                let (span, started) = (trace::client_span(&config), Instant::now());
+
+                #[cfg(feature = "tracing")]
+                let client =
+                    $crate::maybe_async!([$($await)?] in_span(span, Self::connect(config)));
+                #[cfg(not(feature = "tracing"))]
+                let client = Self::connect(config)$(.$await)?;
+
+                #[cfg(feature = "tracing")]
+                trace::record_outcome(&span, started, &client);
+
+                client.map_err(|error| {
+                    if desktop_app {
+                        InitError::DesktopApp(error)
+                    } else {
+                        InitError::Ffi(error)
+                    }
+                })
+            }
+
+            $($async)? fn connect(config: ClientConfig) -> FfiResult<Client> {
+                let timeout = config.timeout;
+                let id = Self::get_client_id(&config, timeout)$(.$await)? ?;
+                let client = Arc::new(ClientInner {
+                    id: AtomicU64::new(id),
+                    closed: AtomicBool::new(false),
+                    lifecycle: $crate::maybe_async!([$($await)?] Mutex::new(())),
+                    names: NameCache::new(config.name_cache_ttl),
+                    config,
+                });
+
+                Ok(Client {
+                    inner: client,
+                    timeout,
+                })
+            }
+
+            pub(crate) fn metrics(&self) -> Option<&dyn MetricsSink> {
+                self.inner.config.metrics.as_deref()
+            }
+
+            /// Returns a handle to the same client whose calls time out after `timeout` instead.
+            ///
+            /// Wrappers created through the handle use its timeout too.
+            pub fn with_timeout(&self, timeout: Option<Duration>) -> Client {
+                Client {
+                    inner: Arc::clone(&self.inner),
+                    timeout,
+                }
+            }
+
+            $($async)? fn get_client_id(
+                config: &ClientConfig,
+                timeout: Option<Duration>,
+            ) -> FfiResult<u64> {
+                onepassword_sys::validate_checksums();
+
+                let serialized_config = config.to_json();
+                let id_buffer = $crate::maybe_async!(
+                    [$($await)?] client_id_buffer(&serialized_config, timeout)
+                )?;
+
+                Ok(id_buffer.to_string().parse().unwrap())
+            }
+
+            /// Checks that the client still works, e.g. that its token hasn't been revoked, with a
+            /// cheap call to the SDK.
+            pub $($async)? fn ping(&self) -> FfiResult<()> {
+                self.invoke::<IgnoredAny>(InvocationParameters::VaultsList { _marker: () })
+                    $(.$await)? ?;
+                Ok(())
+            }
+
+            /// Releases the client on the SDK's side like dropping it does, but reports whether
+            /// that worked. Calls through other handles to the client fail afterwards.
+            pub $($async)? fn close(self) -> FfiResult<()> {
+                let _lifecycle = $crate::maybe_async!([$($await)?] lock(self.lifecycle));
+                if self.closed.swap(true, Ordering::AcqRel) {
+                    return Ok(());
+                }
+
+                onepassword_sys::free_client(&self.id().to_string())
+            }
+
+            /// Creates the client again after the SDK stopped accepting `stale_id`, unless another
+            /// call already did. Returns whether the failed call should be made again, which it
+            /// shouldn't if the client was closed.
+            $($async)? fn reconnect(&self, stale_id: u64) -> FfiResult<bool> {
+                let _lifecycle = $crate::maybe_async!([$($await)?] lock(self.lifecycle));
+                if self.closed.load(Ordering::Acquire) {
+                    return Ok(false);
+                }
+                if self.id() != stale_id {
+                    return Ok(true);
+                }
+
+                let id = Self::get_client_id(&self.config, self.timeout)$(.$await)? ?;
+                self.id.store(id, Ordering::Release);
+                // The SDK has most likely forgotten the old id already, so this is only to be sure.
+                let _ = onepassword_sys::free_client(&stale_id.to_string());
+
+                #[cfg(feature = "tracing")]
+                tracing::info!(
+                    stale_id,
+                    id,
+                    "created the client again after the SDK invalidated it"
+                );
+
+                Ok(true)
+            }
+        }
+
+        impl Client {
+            /// Makes the invocation with this client, retrying it according to its
+            /// [`RetryPolicy`](crate::RetryPolicy). The timeout applies to every attempt
+            /// separately.
+            ///
+            /// If the SDK invalidated the client, it's created again and the call made once more.
+            pub(crate) $($async)? fn invoke<T: DeserializeOwned>(
+                &self,
+                parameters: InvocationParameters,
+            ) -> FfiResult<T> {
+                self.invoke_decoding(parameters, |response| {
+                    serde_json::from_reader(response.as_ref()).unwrap()
+                })
+                $(.$await)?
+            }
+
+            /// Like [`Client::invoke`], for invocations returning a secret. The response is wiped
+            /// once the secret is decoded, see [`secret::decode`].
+            pub(crate) $($async)? fn invoke_secret(
+                &self,
+                parameters: InvocationParameters,
+            ) -> FfiResult<SecretString> {
+                self.invoke_decoding(parameters, |response| secret::decode(response).unwrap())
+                    $(.$await)?
+            }
+
+            $($async)? fn invoke_decoding<T>(
+                &self,
+                parameters: InvocationParameters,
+                decode: impl FnOnce(RustBuffer) -> T,
+            ) -> FfiResult<T> {
+                #[cfg(feature = "tracing")]
+                let (span, started) = (trace::invocation_span(&parameters), Instant::now());
+
+                #[cfg(feature = "tracing")]
+                let result = $crate::maybe_async!(
+                    [$($await)?] in_span(span, self.invoke_with_retries(parameters))
+                );
+                #[cfg(not(feature = "tracing"))]
+                let result = self.invoke_with_retries(parameters)$(.$await)?;
+                let result = result.map(decode);
+
+                #[cfg(feature = "tracing")]
+                trace::record_outcome(&span, started, &result);
+
+                result
+            }
+
+            $($async)? fn invoke_with_retries(
+                &self,
+                parameters: InvocationParameters,
+            ) -> FfiResult<RustBuffer> {
+                let mut attempt = 1;
+                let mut reconnected = false;
+
+                loop {
+                    #[cfg(feature = "tracing")]
+                    tracing::Span::current().record("attempts", attempt);
+
+                    let client_id = self.id();
+                    let invocation = Invocation {
+                        client_id,
+                        parameters: parameters.clone(),
+                    };
+                    let started = Instant::now();
+                    let result = invoke_raw(invocation, self.timeout)$(.$await)?;
+
+                    if let Some(metrics) = &self.config.metrics {
+                        metrics.record_call(&CallRecord {
+                            invocation: parameters.name(),
+                            attempt,
+                            latency: started.elapsed(),
+                            error: result.as_ref().err().map(FfiError::kind),
+                        });
+                    }
+
+                    match result {
+                        Err(e) if e.invalidates_client() && !reconnected => {
+                            if !self.reconnect(client_id)$(.$await)? ? {
+                                return Err(e);
+                            }
+                            reconnected = true;
+                        }
+                        Err(e) => match self.config.retry_policy.backoff(attempt, &e) {
+                            Some(delay) => {
+                                $crate::maybe_async!([$($await)?] sleep(delay));
+                                attempt += 1;
+                            }
+                            None => return Err(e),
+                        },
+                        result => return result,
+                    }
+                }
+            }
+
+            /// Lists the vaults without decoding them, to decode
+            /// [`VaultView`](crate::VaultView)s borrowing from the response instead. The
+            /// listing isn't kept for looking vaults up by name.
+            pub $($async)? fn vault_listing(&self) -> FfiResult<Response> {
+                self.invoke_decoding(
+                    InvocationParameters::VaultsList { _marker: () },
+                    Response::from,
+                )
+                $(.$await)?
+            }
+
+            pub $($async)? fn vaults(&self) -> FfiResult<Vec<VaultWrapper>> {
+                let vaults: Vec<Vault> = self
+                    .invoke(InvocationParameters::VaultsList { _marker: () })
+                    $(.$await)? ?;
+                self.names.set_vaults(vaults.iter().map(Named::from).collect());
+
+                let wrapped_vaults = vaults
+                    .into_iter()
+                    .map(|vault| VaultWrapper {
+                        vault,
+                        client: self.clone(),
+                    })
+                    .collect();
+
+                Ok(wrapped_vaults)
+            }
+
+            #[deprecated(
+                note = "returns an arbitrary match if several vaults share a title, use `vault_by_title`"
+            )]
+            pub $($async)? fn get_vault_by_title(
+                &self,
+                title: &str,
+            ) -> FfiResult<Option<VaultWrapper>> {
+                let vault = self.vaults()$(.$await)? ?.into_iter().find(|v| v.title == title);
+                Ok(vault)
+            }
+
+            /// Looks up a vault by title, failing with [`LookupError::Ambiguous`] if several vaults
+            /// have it.
+            pub $($async)? fn vault_by_title(
+                &self,
+                title: &str,
+            ) -> Result<Option<VaultWrapper>, LookupError> {
+                let vaults = self
+                    .vaults()
+                    $(.$await)? ?
+                    .into_iter()
+                    .filter(|v| v.title == title);
+                single_match(vaults, |v| &v.id)
+            }
+
+            /// Looks up a vault by id or title, reusing the last listing if it's recent enough.
+            pub $($async)? fn vault(
+                &self,
+                name: &str,
+            ) -> Result<Option<VaultWrapper>, LookupError> {
+                let vaults = self.vault_names()$(.$await)? ?;
+                let vault = find_by_name(vaults.iter(), name, |v| &v.id, |v| &v.title)?;
+
+                Ok(vault.map(|vault| VaultWrapper {
+                    vault: Vault {
+                        id: vault.id.clone(),
+                        title: vault.title.clone(),
+                    },
+                    client: self.clone(),
+                }))
+            }
+
+            /// Looks up the id of a vault by id or title, reusing the last listing if it's recent
+            /// enough. Names that look like ids are taken to be ids without listing anything.
+            pub $($async)? fn vault_id(&self, name: &str) -> Result<Option<String>, LookupError> {
+                if is_id(name) {
+                    return Ok(Some(name.to_owned()));
+                }
+
+                let vaults = self.vault_names()$(.$await)? ?;
+                let vault = find_by_name(vaults.iter(), name, |v| &v.id, |v| &v.title)?;
+                Ok(vault.map(|vault| vault.id.clone()))
+            }
+
+            /// Like [`Client::vault_id`], for an item in the vault with id `vault_id`.
+            pub $($async)? fn item_id(
+                &self,
+                vault_id: &str,
+                name: &str,
+            ) -> Result<Option<String>, LookupError> {
+                if is_id(name) {
+                    return Ok(Some(name.to_owned()));
+                }
+
+                let items = self.item_names(vault_id)$(.$await)? ?;
+                let item = find_by_name(items.iter(), name, |it| &it.id, |it| &it.title)?;
+                Ok(item.map(|item| item.id.clone()))
+            }
+
+            /// The ids and titles of the vaults, from the last listing if it's recent enough.
+            $($async)? fn vault_names(&self) -> FfiResult<Arc<[Named]>> {
+                if let Some(vaults) = self.names.vaults() {
+                    return Ok(vaults);
+                }
+
+                let vaults: Vec<Named> = self
+                    .invoke(InvocationParameters::VaultsList { _marker: () })
+                    $(.$await)? ?;
+                let vaults: Arc<[Named]> = vaults.into();
+                self.names.set_vaults(Arc::clone(&vaults));
+
+                Ok(vaults)
+            }
+
+            /// The ids and titles of the items in the vault with id `vault_id`, from the last
+            /// listing if it's recent enough.
+            $($async)? fn item_names(&self, vault_id: &str) -> FfiResult<Arc<[Named]>> {
+                if let Some(items) = self.names.items(vault_id) {
+                    return Ok(items);
+                }
+
+                let items: Vec<Named> = self
+                    .invoke(InvocationParameters::ItemsList {
+                        vault_id: vault_id.to_owned(),
+                        filters: vec![],
+                    })
+                    $(.$await)? ?;
+                let items: Arc<[Named]> = items.into();
+                self.names.set_items(vault_id, Arc::clone(&items));
+
+                Ok(items)
+            }
+
+            /// Resolves a secret reference, replacing vault and item titles with their ids first so
+            /// repeated resolutions don't make the SDK look them up again.
+            pub $($async)? fn resolve(&self, reference: &str) -> Result<SecretString, LookupError> {
+                let secret_reference = self.canonicalize_reference(reference)$(.$await)? ?;
+
+                let secret = self.resolve_exact(secret_reference)$(.$await)? ?;
+                Ok(secret)
+            }
+
+            /// Resolves a secret reference as it is, for references already built from ids.
+            pub(crate) $($async)? fn resolve_exact(
+                &self,
+                secret_reference: String,
+            ) -> FfiResult<SecretString> {
+                self.invoke_secret(InvocationParameters::SecretsResolve { secret_reference })
+                    $(.$await)?
+            }
+
+            $($async)? fn canonicalize_reference(
+                &self,
+                reference: &str,
+            ) -> Result<String, LookupError> {
+                let Some(parsed) = SecretReference::parse(reference) else {
+                    return Ok(reference.to_owned());
+                };
+
+                let ids = match self.vault_id(parsed.vault)$(.$await)? {
+                    Ok(Some(vault_id)) => self
+                        .item_id(&vault_id, parsed.item)
+                        $(.$await)?
+                        .map(|item_id| item_id.map(|item_id| (vault_id, item_id))),
+                    other => other.map(|_| None),
+                };
+
+                match ids {
+                    Ok(Some((vault_id, item_id))) => Ok(parsed.with_ids(&vault_id, &item_id)),
+                    // The SDK may still resolve the reference itself, e.g. if only listing failed.
+                    Ok(None) | Err(LookupError::Ffi(_)) => Ok(reference.to_owned()),
+                    Err(error) => Err(error),
+                }
+            }
+
+            /// Forgets every cached vault and item name, e.g. after writing to them elsewhere.
+            pub fn invalidate_names(&self) {
+                self.names.invalidate();
+            }
+
+            /// Forgets the cached item names of one vault, leaving the rest cached.
+            pub fn invalidate_vault_names(&self, vault_id: &str) {
+                self.names.invalidate_vault(vault_id);
+            }
+
+            pub $($async)? fn vault_by_id(&self, id: &str) -> FfiResult<Option<VaultWrapper>> {
+                let vault = self.vaults()$(.$await)? ?.into_iter().find(|v| v.id == id);
+                Ok(vault)
+            }
+
+            /// Searches the items of every vault the client can access, most relevant first.
+            ///
+            /// The async client lists up to [`search::CONCURRENCY`] vaults at once. See
+            /// [`search::relevance`] for how items are matched.
+            pub $($async)? fn search(&self, query: &str) -> FfiResult<Vec<ItemWrapper>> {
+                let vaults = self.vaults()$(.$await)? ?;
+                let items = vaults.iter().map(VaultWrapper::items);
+                let items = $crate::maybe_async!([$($await)?] try_buffered(items, search::CONCURRENCY))?;
+
+                Ok(search::rank(items.into_iter().flatten(), query))
+            }
+        }
+
+        pub struct VaultWrapper {
+            pub vault: Vault,
+            client: Client,
+        }
+
+        impl std::fmt::Debug for VaultWrapper {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct("VaultWrapper")
+                    .field("vault", &self.vault)
+                    .finish()
+            }
+        }
+
+        impl Deref for VaultWrapper {
+            type Target = Vault;
+
+            fn deref(&self) -> &Self::Target {
+                &self.vault
+            }
+        }
+
+        impl VaultWrapper {
+            pub $($async)? fn items(&self) -> FfiResult<Vec<ItemWrapper>> {
+                let items = self
+                    .client
+                    .invoke::<Vec<Item>>(InvocationParameters::ItemsList {
+                        vault_id: self.vault.id.clone(),
+                        filters: vec![],
+                    })
+                    $(.$await)? ?;
+                self.client
+                    .names
+                    .set_items(&self.vault.id, items.iter().map(Named::from).collect());
+
+                let vault_id: Arc<str> = self.vault.id.as_str().into();
+                let items = items
+                    .into_iter()
+                    .map(|item| ItemWrapper {
+                        item,
+                        client: self.client.clone(),
+                        vault_id: Arc::clone(&vault_id),
+                    })
+                    .collect();
+
+                Ok(items)
+            }
+
+            /// Lists the items without decoding them, to decode
+            /// [`ItemView`](crate::ItemView)s borrowing from the response instead. The
+            /// listing isn't kept for looking items up by name.
+            pub $($async)? fn item_listing(&self) -> FfiResult<Response> {
+                self.client
+                    .invoke_decoding(
+                        InvocationParameters::ItemsList {
+                            vault_id: self.vault.id.clone(),
+                            filters: vec![],
+                        },
+                        Response::from,
+                    )
+                    $(.$await)?
+            }
+
+            /// Like [`VaultWrapper::items`], but decodes the items one at a time as they're
+            /// iterated over, which for huge vaults uses far less memory and gets to the first item
+            /// sooner. The listing isn't kept for looking items up by name.
+            pub $($async)? fn items_iter(&self) -> FfiResult<ItemIter> {
+                let items = self
+                    .client
+                    .invoke_decoding(
+                        InvocationParameters::ItemsList {
+                            vault_id: self.vault.id.clone(),
+                            filters: vec![],
+                        },
+                        JsonArray::new,
+                    )
+                    $(.$await)? ?;
+
+                Ok(ItemIter {
+                    items,
+                    client: self.client.clone(),
+                    vault_id: self.vault.id.as_str().into(),
+                })
+            }
+
+            /// Looks up an item by title, failing with [`LookupError::Ambiguous`] if several items
+            /// have it.
+            pub $($async)? fn item_by_title(
+                &self,
+                title: &str,
+            ) -> Result<Option<ItemWrapper>, LookupError> {
+                let items = self
+                    .items()
+                    $(.$await)? ?
+                    .into_iter()
+                    .filter(|it| it.title == title);
+                single_match(items, |it| &it.id)
+            }
+
+            /// Looks up an item by id or title. Only the ids and titles of listings are cached, so
+            /// this lists the items every time, see [`Client::item_id`] for just the id.
+            pub $($async)? fn item(&self, name: &str) -> Result<Option<ItemWrapper>, LookupError> {
+                let items = self.items()$(.$await)? ?;
+                find_by_name(items, name, |it| &it.id, |it| &it.title)
+            }
+
+            pub $($async)? fn item_by_id(&self, id: &str) -> FfiResult<Option<ItemWrapper>> {
+                let item = self.items()$(.$await)? ?.into_iter().find(|it| it.id == id);
+                Ok(item)
+            }
+
+            pub $($async)? fn items_for_website(
+                &self,
+                website: &str,
+            ) -> FfiResult<Vec<ItemWrapper>> {
+                let trim_protocol = !website.contains("://");
+                let items = self
+                    .items()
+                    $(.$await)? ?
+                    .into_iter()
+                    .filter(|it| {
+                        it.websites.iter().any(|w| {
+                            if trim_protocol {
+                                w.url
+                                    .split_once("://")
+                                    .is_some_and(|(_, url)| website.starts_with(url))
+                            } else {
+                                website.starts_with(&w.url)
+                            }
+                        })
+                    })
+                    .collect();
+
+                Ok(items)
+            }
+        }
+
+        /// The items of a vault, decoded as they're iterated over, see
+        /// [`VaultWrapper::items_iter`].
+        pub struct ItemIter {
+            items: JsonArray<Item, RustBuffer>,
+            client: Client,
+            vault_id: Arc<str>,
+        }
+
+        impl Iterator for ItemIter {
+            type Item = serde_json::Result<ItemWrapper>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                let item = self.items.next()?;
+
+                Some(item.map(|item| ItemWrapper {
+                    item,
+                    client: self.client.clone(),
+                    vault_id: Arc::clone(&self.vault_id),
+                }))
+            }
+        }
+
+        pub struct ItemWrapper {
+            pub item: Item,
+            client: Client,
+            vault_id: Arc<str>,
+        }
+
+        impl std::fmt::Debug for ItemWrapper {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct("ItemWrapper")
+                    .field("item", &self.item)
+                    .field("vault_id", &self.vault_id)
+                    .finish()
+            }
+        }
+
+        impl Deref for ItemWrapper {
+            type Target = Item;
+
+            fn deref(&self) -> &Self::Target {
+                &self.item
+            }
+        }
+
+        impl ItemWrapper {
+            pub fn vault_id(&self) -> &str {
+                &self.vault_id
+            }
+
+            pub(crate) fn construct_secret_ref(&self, field: &str) -> String {
+                format!("op://{}/{}/{field}", self.vault_id, self.item.id)
+            }
+        }
+
+        impl ItemWrapper {
+            pub $($async)? fn password(&self) -> FfiResult<Option<SecretString>> {
+                let secret_reference = self.construct_secret_ref("password");
+
+                match self.client.resolve_exact(secret_reference)$(.$await)? {
+                    Ok(secret) => Ok(Some(secret)),
+                    Err(e) if e.code() == 133 => Ok(None),
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    };
+}
+
+/// Defines `ClientRegistry` for the `Client` defined by [`define_wrappers!`], with either
+/// blocking (`sync`) or async (`async`) methods.
+#[doc(hidden)]
+#[macro_export]
+#[expect(
+    clippy::crate_in_macro_def,
+    reason = "`Client` is the expanding crate's"
+)]
+macro_rules! define_registry {
+    (sync) => {
+        $crate::define_registry!(@define [] []);
+    };
+    (async) => {
+        $crate::define_registry!(@define [async] [await]);
+    };
+    (@define [$($async:tt)?] [$($await:tt)?]) => {
+        use crate::Client;
+        use $crate::{
+            __private::secrecy::SecretString, errors::RegistryError, registry::RegistryConfig,
+        };
+        use std::{collections::HashMap, path::Path};
+
+        /// Creates and shares a [`Client`] per account configured in a [`RegistryConfig`], and
+        /// routes secret references to them.
+        ///
+        /// Clients are only created when an account is first used.
+        pub struct ClientRegistry {
+            config: RegistryConfig,
+            clients: $crate::maybe_async!([$($await)?] Mutex(HashMap<String, Client>)),
+        }
+
+        impl ClientRegistry {
+            /// Fails if `config` routes to accounts it doesn't configure, see
+            /// [`RegistryConfig::validate`].
+            pub fn new(config: RegistryConfig) -> Result<Self, RegistryError> {
+                config.validate()?;
+
+                Ok(Self {
+                    config,
+                    clients: $crate::maybe_async!([$($await)?] Mutex::new(HashMap::new())),
+                })
+            }
+
+            pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RegistryError> {
+                Self::new(RegistryConfig::load(path)?)
+            }
+
+            pub fn config(&self) -> &RegistryConfig {
+                &self.config
+            }
+
+            /// Returns the client for `account`, creating it if it's the first time it's used.
+            ///
+            /// Other accounts stay usable while the client is created. If it's created for the same
+            /// account twice at once, the first one to finish is kept.
+            pub $($async)? fn client(&self, account: &str) -> Result<Client, RegistryError> {
+                let existing = $crate::maybe_async!([$($await)?] lock(self.clients))
+                    .get(account)
+                    .cloned();
+                if let Some(client) = existing {
+                    return Ok(client);
+                }
+
+                let config = self.config.client_config(account)?;
+                let client =
+                    Client::new(config)
+                        $(.$await)?
+                        .map_err(|source| RegistryError::Init {
+                            account: account.to_owned(),
+                            source,
+                        })?;
+
+                let client = $crate::maybe_async!([$($await)?] lock(self.clients))
+                    .entry(account.to_owned())
+                    .or_insert(client)
+                    .clone();
+                Ok(client)
+            }
+
+            /// Resolves a secret reference with the account it's routed to, see
+            /// [`RegistryConfig::route`].
+            pub $($async)? fn resolve(
+                &self,
+                reference: &str,
+            ) -> Result<SecretString, RegistryError> {
+                let (account, reference) = self.config.route(reference)?;
+
+                self.client(account)
+                    $(.$await)? ?
+                    .resolve(reference)
+                    $(.$await)?
+                    .map_err(|source| RegistryError::Lookup {
+                        account: account.to_owned(),
+                        source,
+                    })
+            }
+
+            /// Closes every client created so far, see [`Client::close`]. Using an account
+            /// afterwards creates its client again.
+            pub $($async)? fn close(&self) -> Result<(), RegistryError> {
+                let clients = std::mem::take(
+                    &mut *$crate::maybe_async!([$($await)?] lock(self.clients)),
+                );
+
+                let mut errors = Vec::new();
+                for (account, client) in clients {
+                    if let Err(error) = client.close()$(.$await)? {
+                        errors.push((account, error));
+                    }
+                }
+
+                if errors.is_empty() {
+                    Ok(())
+                } else {
+                    Err(RegistryError::Close { errors })
+                }
+            }
+        }
+    };
+}
+
+/// Defines `CachingClient` for the `Client` defined by [`define_wrappers!`], with either blocking
+/// (`sync`) or async (`async`) methods.
+///
+/// Refreshing secrets ahead of their expiry is left to the runtime, so the expanding module has
+/// to have a `refresher` submodule with a `Refresher` that has:
+///
+/// - `fn new(client: &Client, cache: &Arc<SecretCache>) -> Refresher`, called once per
+///   caching client.
+/// - `fn queue(&self, reference: &str) -> bool`, which queues a reference whose refresh is due
+///   and returns whether that worked. Once refreshed, the secret has to be passed to
+///   [`SecretCache::refreshed`](crate::cache::SecretCache::refreshed), or if that failed, the
+///   reference to [`SecretCache::refresh_failed`](crate::cache::SecretCache::refresh_failed).
+#[doc(hidden)]
+#[macro_export]
+#[expect(
+    clippy::crate_in_macro_def,
+    reason = "`Client` is the expanding crate's"
+)]
+macro_rules! define_caching {
+    (sync) => {
+        $crate::define_caching!(@define [] []);
+    };
+    (async) => {
+        $crate::define_caching!(@define [async] [await]);
+    };
+    (@define [$($async:tt)?] [$($await:tt)?]) => {
+        use crate::{Client, wrappers::ItemWrapper};
+        use $crate::{
+            __private::secrecy::SecretString,
+            cache::{CacheConfig, CacheLookup, SecretCache},
+            errors::LookupError,
+        };
+        use std::{sync::Arc, time::Duration};
+
+        /// Wraps a [`Client`] and caches resolved secrets, see [`CacheConfig`] for the knobs.
+        ///
+        /// If [`CacheConfig::refresh_ahead`] is set, secrets used shortly before they expire are
+        /// refreshed in the background. In `onepassword` that's done by a thread, which exits
+        /// once every clone of the caching client is dropped. In `onepassword-async` it's done by
+        /// the future returned from `CachingClient::refresher`, which has to be spawned on the
+        /// caller's executor.
+        #[derive(Clone)]
+        pub struct CachingClient(Arc<CachingClientInner>);
+
+        struct CachingClientInner {
+            client: Client,
+            cache: Arc<SecretCache>,
+            refresher: refresher::Refresher,
+        }
+
+        impl CachingClient {
+            pub fn new(client: Client, config: CacheConfig) -> Self {
+                let cache = Arc::new(SecretCache::new(config));
+                let refresher = refresher::Refresher::new(&client, &cache);
+
+                Self(Arc::new(CachingClientInner {
+                    client,
+                    cache,
+                    refresher,
+                }))
+            }
+
+            pub fn client(&self) -> &Client {
+                &self.0.client
+            }
+
+            pub $($async)? fn resolve(&self, reference: &str) -> Result<SecretString, LookupError> {
+                self.resolve_cached(reference, None, false)$(.$await)?
+            }
+
+            /// Like [`CachingClient::resolve`], but caches the secret for `ttl` instead of the
+            /// default.
+            pub $($async)? fn resolve_with_ttl(
+                &self,
+                reference: &str,
+                ttl: Duration,
+            ) -> Result<SecretString, LookupError> {
+                self.resolve_cached(reference, Some(ttl), false)$(.$await)?
+            }
+
+            /// Cached version of [`ItemWrapper::password`].
+            pub $($async)? fn password(
+                &self,
+                item: &ItemWrapper,
+            ) -> Result<Option<SecretString>, LookupError> {
+                let reference = item.construct_secret_ref("password");
+                match self.resolve_cached(&reference, None, true)$(.$await)? {
+                    Ok(secret) => Ok(Some(secret)),
+                    Err(LookupError::Ffi(e)) if e.code() == 133 => Ok(None),
+                    Err(e) => Err(e),
+                }
+            }
+
+            pub fn invalidate(&self, reference: &str) {
+                self.0.cache.invalidate(reference);
+            }
+
+            pub fn invalidate_all(&self) {
+                self.0.cache.clear();
+            }
+
+            /// Resolves `reference` on a cache miss, which if it's `exact` is made of ids already,
+            /// so it's resolved without looking up vault and item titles.
+            $($async)? fn resolve_cached(
+                &self,
+                reference: &str,
+                ttl: Option<Duration>,
+                exact: bool,
+            ) -> Result<SecretString, LookupError> {
+                let CachingClientInner {
+                    client,
+                    cache,
+                    refresher,
+                } = &*self.0;
+
+                let lookup = cache.get(reference);
+                if let Some(metrics) = client.metrics() {
+                    metrics.record_cache_lookup(!matches!(lookup, CacheLookup::Miss));
+                }
+
+                match lookup {
+                    CacheLookup::Hit(secret) => return Ok(secret),
+                    CacheLookup::RefreshDue(secret) => {
+                        if !refresher.queue(reference) {
+                            cache.refresh_failed(reference);
+                        }
+
+                        return Ok(secret);
+                    }
+                    CacheLookup::Miss => {}
+                }
+
+                let secret = if exact {
+                    client.resolve_exact(reference.to_owned())$(.$await)? ?
+                } else {
+                    client.resolve(reference)$(.$await)? ?
+                };
+                cache.insert(reference, secret.clone(), ttl);
+                Ok(secret)
+            }
+        }
+    };
+}
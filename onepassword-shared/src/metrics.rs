@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use onepassword_sys::ErrorKind;
+
+/// One call made to the SDK. Retried invocations are reported once per attempt, since every
+/// attempt counts against the service account's rate limit.
+#[derive(Debug, Clone, Copy)]
+pub struct CallRecord {
+    /// The SDK's name for the invocation, e.g. `"SecretsResolve"`.
+    pub invocation: &'static str,
+    /// Starting at 1 for the first attempt.
+    pub attempt: u32,
+    pub latency: Duration,
+    pub error: Option<ErrorKind>,
+}
+
+/// Receives call and cache statistics from a client, e.g. to export them to Prometheus.
+///
+/// Implementations are called inline on every call, so they should be cheap.
+pub trait MetricsSink: Send + Sync {
+    fn record_call(&self, call: &CallRecord);
+
+    /// Called for every lookup in a `CachingClient`'s cache.
+    fn record_cache_lookup(&self, hit: bool) {
+        let _ = hit;
+    }
+}
+
+/// Reports to the [`metrics`] facade, under:
+/// - `onepassword_calls_total`, labelled by `invocation` and `outcome` (`ok` or `error`),
+/// - `onepassword_call_duration_seconds`, a histogram labelled by `invocation`,
+/// - `onepassword_errors_total`, labelled by `invocation` and `kind`,
+/// - `onepassword_cache_lookups_total`, labelled by `result` (`hit` or `miss`).
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MetricsFacade;
+
+#[cfg(feature = "metrics")]
+impl MetricsSink for MetricsFacade {
+    fn record_call(&self, call: &CallRecord) {
+        let outcome = if call.error.is_some() { "error" } else { "ok" };
+
+        metrics::counter!(
+            "onepassword_calls_total",
+            "invocation" => call.invocation,
+            "outcome" => outcome
+        )
+        .increment(1);
+        metrics::histogram!(
+            "onepassword_call_duration_seconds",
+            "invocation" => call.invocation
+        )
+        .record(call.latency);
+
+        if let Some(kind) = call.error {
+            metrics::counter!(
+                "onepassword_errors_total",
+                "invocation" => call.invocation,
+                "kind" => kind.as_str()
+            )
+            .increment(1);
+        }
+    }
+
+    fn record_cache_lookup(&self, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        metrics::counter!("onepassword_cache_lookups_total", "result" => result).increment(1);
+    }
+}
@@ -0,0 +1,46 @@
+//! Detection of the machine details 1Password records for every client, e.g. in the audit log.
+
+/// The operating system, named the way the other 1Password SDKs name it.
+pub fn os() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    }
+}
+
+pub fn architecture() -> &'static str {
+    std::env::consts::ARCH
+}
+
+/// The kernel release reported by `uname`, e.g. `6.8.0-45-generic` on Linux.
+#[cfg(unix)]
+pub fn os_version() -> Option<String> {
+    // SAFETY: `utsname` is plain old data, and `uname` fills it with NUL-terminated strings.
+    let mut name = unsafe { core::mem::zeroed::<libc::utsname>() };
+    if unsafe { libc::uname(&mut name) } != 0 {
+        return None;
+    }
+
+    let release = unsafe { core::ffi::CStr::from_ptr(name.release.as_ptr()) };
+    Some(release.to_string_lossy().into_owned())
+}
+
+/// Not detected on this platform yet.
+#[cfg(not(unix))]
+pub fn os_version() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_the_current_platform() {
+        assert!(!os().is_empty());
+        assert_eq!(architecture(), std::env::consts::ARCH);
+
+        #[cfg(unix)]
+        assert!(os_version().is_some_and(|version| !version.is_empty()));
+    }
+}
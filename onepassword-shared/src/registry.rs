@@ -0,0 +1,201 @@
+//! The config file for a registry of clients for several accounts, e.g. `ClientRegistry` in
+//! `onepassword` and `onepassword-async`.
+//!
+//! ```json
+//! {
+//!     "accounts": {
+//!         "prod": { "tokenEnv": "OP_PROD_TOKEN" },
+//!         "staging": { "tokenFile": "/etc/onepassword/staging-token" },
+//!         "dev": { "desktopApp": "my.1password.com" }
+//!     },
+//!     "defaultAccount": "prod",
+//!     "routes": { "op://Staging/": "staging" }
+//! }
+//! ```
+//!
+//! References are routed to an account by an `account:` prefix, as in
+//! `staging:op://vault/item/field`, failing that by the longest matching prefix in `routes`, and
+//! failing that to the default account.
+
+use std::{collections::HashMap, fs, path::Path, process::Command};
+
+use crate::{errors::RegistryError, types::ClientConfig};
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RegistryConfig {
+    pub accounts: HashMap<String, AccountConfig>,
+    #[serde(default)]
+    pub default_account: Option<String>,
+    /// Reference prefixes mapped to the account they're resolved with.
+    #[serde(default)]
+    pub routes: HashMap<String, String>,
+}
+
+/// Where an account's client gets its credentials, see
+/// [`ClientConfigBuilder`](crate::config::ClientConfigBuilder).
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub enum AccountConfig {
+    TokenEnv(String),
+    TokenFile(std::path::PathBuf),
+    TokenCommand {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    DesktopApp(String),
+}
+
+impl RegistryConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RegistryError> {
+        let path = path.as_ref();
+        let contents = fs::read(path).map_err(|source| RegistryError::ReadFile {
+            path: path.to_owned(),
+            source,
+        })?;
+
+        let config: Self =
+            serde_json::from_slice(&contents).map_err(|source| RegistryError::Parse {
+                path: path.to_owned(),
+                source,
+            })?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that the default account and every route refer to a configured account.
+    pub fn validate(&self) -> Result<(), RegistryError> {
+        let mut routed = self.default_account.iter().chain(self.routes.values());
+        match routed.find(|a| !self.accounts.contains_key(*a)) {
+            Some(account) => Err(RegistryError::UnknownAccount {
+                account: account.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Builds the client config for `account`, reading its token if it uses one.
+    pub fn client_config(&self, account: &str) -> Result<ClientConfig, RegistryError> {
+        let auth = self
+            .accounts
+            .get(account)
+            .ok_or_else(|| RegistryError::UnknownAccount {
+                account: account.to_owned(),
+            })?;
+
+        let builder = ClientConfig::builder();
+        let builder = match auth {
+            AccountConfig::TokenEnv(variable) => builder.token_from_env(variable),
+            AccountConfig::TokenFile(path) => builder.token_from_file(path),
+            AccountConfig::TokenCommand { program, args } => {
+                let mut command = Command::new(program);
+                command.args(args);
+                builder.token_from_command(command)
+            }
+            AccountConfig::DesktopApp(account_name) => builder.desktop_app(account_name),
+        };
+
+        builder.build().map_err(|source| RegistryError::Config {
+            account: account.to_owned(),
+            source,
+        })
+    }
+
+    /// Picks the account to resolve `reference` with, and strips the account prefix if it has
+    /// one.
+    pub fn route<'a>(&'a self, reference: &'a str) -> Result<(&'a str, &'a str), RegistryError> {
+        if let Some(index) = reference.find(":op://") {
+            let (account, reference) = (&reference[..index], &reference[index + 1..]);
+            if !self.accounts.contains_key(account) {
+                return Err(RegistryError::UnknownAccount {
+                    account: account.to_owned(),
+                });
+            }
+
+            return Ok((account, reference));
+        }
+
+        let route = self
+            .routes
+            .iter()
+            .filter(|(prefix, _)| reference.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len());
+
+        match route
+            .map(|(_, account)| account)
+            .or(self.default_account.as_ref())
+        {
+            Some(account) => Ok((account, reference)),
+            None => Err(RegistryError::NoAccount {
+                reference: reference.to_owned(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RegistryConfig {
+        serde_json::from_str(
+            r#"{
+                "accounts": {
+                    "prod": { "tokenEnv": "OP_PROD_TOKEN" },
+                    "staging": { "tokenCommand": { "program": "echo", "args": ["ops_staging"] } }
+                },
+                "defaultAccount": "prod",
+                "routes": { "op://Staging/": "staging" }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn routes_references_to_accounts() {
+        let config = config();
+
+        assert_eq!(
+            config.route("staging:op://Prod/db/password").unwrap(),
+            ("staging", "op://Prod/db/password")
+        );
+        assert_eq!(
+            config.route("op://Staging/db/password").unwrap(),
+            ("staging", "op://Staging/db/password")
+        );
+        assert_eq!(
+            config.route("op://Prod/db/password").unwrap(),
+            ("prod", "op://Prod/db/password")
+        );
+        assert!(matches!(
+            config.route("qa:op://Prod/db/password"),
+            Err(RegistryError::UnknownAccount { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_routes_to_unknown_accounts() {
+        assert!(config().validate().is_ok());
+
+        let mut config = config();
+        config.routes.insert("op://QA/".to_owned(), "qa".to_owned());
+        assert!(matches!(
+            config.validate(),
+            Err(RegistryError::UnknownAccount { account }) if account == "qa"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn builds_client_configs_per_account() {
+        use secrecy::ExposeSecret;
+
+        let config = config().client_config("staging").unwrap();
+        let crate::types::Auth::ServiceAccount(token) = &config.auth else {
+            panic!("expected a service account");
+        };
+        assert_eq!(token.expose_secret(), "ops_staging");
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    errors::{LookupError, single_match},
+    types::{Item, Vault},
+};
+
+/// The id and title of a vault or item, which is all that's needed to look it up by name.
+///
+/// Decodes from listed vaults and items, skipping everything else about them.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Named {
+    pub id: String,
+    pub title: String,
+}
+
+impl From<&Vault> for Named {
+    fn from(vault: &Vault) -> Self {
+        Self {
+            id: vault.id.clone(),
+            title: vault.title.clone(),
+        }
+    }
+}
+
+impl From<&Item> for Named {
+    fn from(item: &Item) -> Self {
+        Self {
+            id: item.id.clone(),
+            title: item.title.clone(),
+        }
+    }
+}
+
+struct Entry {
+    fetched_at: Instant,
+    names: Arc<[Named]>,
+}
+
+impl Entry {
+    fn new(names: Arc<[Named]>) -> Self {
+        Self {
+            fetched_at: Instant::now(),
+            names,
+        }
+    }
+
+    /// Shares the names, so a hit doesn't copy them.
+    fn get(&self, ttl: Duration) -> Option<Arc<[Named]>> {
+        (self.fetched_at.elapsed() < ttl).then(|| Arc::clone(&self.names))
+    }
+}
+
+/// Remembers the ids and titles of the vaults and items a client has listed, so names can be
+/// resolved to ids without listing them again.
+///
+/// Listings older than the TTL are treated as missing.
+pub struct NameCache {
+    ttl: Duration,
+    vaults: Mutex<Option<Entry>>,
+    items: Mutex<HashMap<String, Entry>>,
+}
+
+impl NameCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            vaults: Mutex::new(None),
+            items: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn vaults(&self) -> Option<Arc<[Named]>> {
+        let vaults = self.vaults.lock().unwrap();
+        vaults.as_ref()?.get(self.ttl)
+    }
+
+    pub fn set_vaults(&self, vaults: Arc<[Named]>) {
+        *self.vaults.lock().unwrap() = Some(Entry::new(vaults));
+    }
+
+    pub fn items(&self, vault_id: &str) -> Option<Arc<[Named]>> {
+        let items = self.items.lock().unwrap();
+        items.get(vault_id)?.get(self.ttl)
+    }
+
+    pub fn set_items(&self, vault_id: &str, items: Arc<[Named]>) {
+        let mut cached = self.items.lock().unwrap();
+        cached.insert(vault_id.to_owned(), Entry::new(items));
+    }
+
+    /// Forgets the items of one vault, e.g. after something in it was written.
+    pub fn invalidate_vault(&self, vault_id: &str) {
+        self.items.lock().unwrap().remove(vault_id);
+    }
+
+    pub fn invalidate(&self) {
+        *self.vaults.lock().unwrap() = None;
+        self.items.lock().unwrap().clear();
+    }
+}
+
+/// Whether `name` looks like a 1Password id, which is 26 lowercase base32 characters, so it can
+/// be used without looking it up.
+pub fn is_id(name: &str) -> bool {
+    name.len() == 26
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || (b'2'..=b'7').contains(&b))
+}
+
+/// Finds the candidate whose id is `name`, or failing that the only one titled `name`.
+pub fn find_by_name<T>(
+    candidates: impl IntoIterator<Item = T>,
+    name: &str,
+    id: impl Fn(&T) -> &str,
+    title: impl Fn(&T) -> &str,
+) -> Result<Option<T>, LookupError> {
+    let mut titled = Vec::new();
+
+    for candidate in candidates {
+        if id(&candidate) == name {
+            return Ok(Some(candidate));
+        }
+        if title(&candidate) == name {
+            titled.push(candidate);
+        }
+    }
+
+    single_match(titled, id)
+}
+
+/// The parts of an `op://<vault>/<item>/[<section>/]<field>` secret reference.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SecretReference<'a> {
+    pub vault: &'a str,
+    pub item: &'a str,
+    pub field: &'a str,
+}
+
+impl<'a> SecretReference<'a> {
+    pub fn parse(reference: &'a str) -> Option<Self> {
+        let path = reference.strip_prefix("op://")?;
+        let (vault, path) = path.split_once('/')?;
+        let (item, field) = path.split_once('/')?;
+
+        if vault.is_empty() || item.is_empty() || field.is_empty() {
+            return None;
+        }
+
+        Some(Self { vault, item, field })
+    }
+
+    pub fn with_ids(&self, vault_id: &str, item_id: &str) -> String {
+        format!("op://{vault_id}/{item_id}/{}", self.field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_references() {
+        assert_eq!(
+            SecretReference::parse("op://Shared/Staging Postgres/db/password"),
+            Some(SecretReference {
+                vault: "Shared",
+                item: "Staging Postgres",
+                field: "db/password",
+            })
+        );
+        assert_eq!(SecretReference::parse("op://Shared/password"), None);
+        assert_eq!(SecretReference::parse("Shared/item/password"), None);
+    }
+
+    #[test]
+    fn ids_take_precedence_over_titles() {
+        let vaults = [("a", "Shared"), ("b", "Shared"), ("c", "a")];
+
+        let by_id = find_by_name(vaults, "a", |v| v.0, |v| v.1).unwrap();
+        assert_eq!(by_id, Some(("a", "Shared")));
+
+        let Err(LookupError::Ambiguous { ids }) = find_by_name(vaults, "Shared", |v| v.0, |v| v.1)
+        else {
+            panic!("expected an ambiguous lookup");
+        };
+        assert_eq!(ids, ["a", "b"]);
+    }
+
+    #[test]
+    fn recognizes_ids() {
+        assert!(is_id("jqyuqkvbo5gwbxsyv6fztjbcfq"));
+        assert!(!is_id("Shared"));
+        assert!(!is_id("JQYUQKVBO5GWBXSYV6FZTJBCFQ"));
+        assert!(!is_id("jqyuqkvbo5gwbxsyv6fztjbcf1"));
+    }
+
+    #[test]
+    fn listings_expire() {
+        let names: Arc<[Named]> = Arc::new([Named {
+            id: "a".to_owned(),
+            title: "Shared".to_owned(),
+        }]);
+
+        let cache = NameCache::new(Duration::ZERO);
+        cache.set_vaults(Arc::clone(&names));
+        assert!(cache.vaults().is_none());
+
+        let cache = NameCache::new(Duration::from_secs(60));
+        cache.set_vaults(Arc::clone(&names));
+        assert!(Arc::ptr_eq(&names, &cache.vaults().unwrap()));
+
+        cache.set_items("a", Arc::new([]));
+        cache.set_items("b", Arc::new([]));
+        cache.invalidate_vault("a");
+        assert!(cache.items("a").is_none());
+        assert!(cache.items("b").is_some());
+    }
+
+    #[test]
+    fn decodes_only_ids_and_titles() {
+        let names: Vec<Named> = serde_json::from_str(
+            r#"[{"id": "a", "title": "Login", "category": "Login", "websites": [], "tags": []}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            (names[0].id.as_str(), names[0].title.as_str()),
+            ("a", "Login")
+        );
+    }
+}
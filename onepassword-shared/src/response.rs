@@ -0,0 +1,66 @@
+//! Undecoded SDK responses, for decoding views borrowing from them instead of owned values.
+
+use onepassword_sys::RustBuffer;
+use serde::Deserialize;
+
+/// A response as the SDK returned it, which views like [`ItemView`](crate::types::ItemView) can
+/// borrow their strings from, so listing many items doesn't copy each string into a `String`.
+pub struct Response(RustBuffer);
+
+impl Response {
+    /// Decodes `T`, which borrows from the response for as long as it's alive.
+    pub fn decode<'a, T: Deserialize<'a>>(&'a self) -> serde_json::Result<T> {
+        serde_json::from_slice(self.0.as_ref())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl From<RustBuffer> for Response {
+    fn from(buffer: RustBuffer) -> Self {
+        Self(buffer)
+    }
+}
+
+impl std::fmt::Debug for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Response")
+            .field("len", &self.0.len)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Item, ItemView};
+    use std::borrow::Cow;
+
+    #[test]
+    fn decodes_borrowed_views() {
+        let json = br#"[{
+            "id": "abc",
+            "title": "Caf\u00e9",
+            "category": "Login",
+            "websites": [{"url": "https://example.com"}],
+            "tags": ["work"]
+        }]"#;
+        let response = Response::from(RustBuffer::from(&json[..]));
+
+        let items: Vec<ItemView> = response.decode().unwrap();
+        let item = &items[0];
+        assert!(matches!(item.id, Cow::Borrowed("abc")));
+        assert!(matches!(
+            item.websites[0].url,
+            Cow::Borrowed("https://example.com")
+        ));
+        assert!(matches!(item.tags[0], Cow::Borrowed("work")));
+        // Unescaping needs a copy.
+        assert!(matches!(&item.title, Cow::Owned(title) if title == "Café"));
+
+        let item = Item::from(items.into_iter().next().unwrap());
+        assert_eq!(item.tags, ["work"]);
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    hash::{BuildHasher, RandomState},
+    time::Duration,
+};
+
+use onepassword_sys::Error as FfiError;
+
+/// When and how often calls failing with a transient error (see [`FfiError::is_transient`]) are
+/// retried.
+///
+/// The backoff doubles (by default) after every attempt, up to `max_backoff`. With `jitter`
+/// every delay is picked at random between half of it and all of it, so clients that failed
+/// together don't retry together. A delay the SDK asked for takes precedence, and if it's longer
+/// than `max_backoff` the call isn't retried at all, so the error surfaces instead of the call
+/// going out again before the SDK would accept it.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// How many times a call is made at most, including the first attempt.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: u32,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Makes every call exactly once.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// How long to wait before retrying a call whose `attempt`th attempt (starting at 1) failed
+    /// with `error`, or `None` if it shouldn't be retried.
+    pub fn backoff(&self, attempt: u32, error: &FfiError) -> Option<Duration> {
+        if attempt >= self.max_attempts || !error.is_transient() {
+            return None;
+        }
+
+        if let Some(retry_after) = error.retry_after() {
+            return (retry_after <= self.max_backoff).then_some(retry_after);
+        }
+
+        let factor = self.multiplier.saturating_pow(attempt - 1);
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+
+        if !self.jitter {
+            return Some(backoff);
+        }
+
+        let random = RandomState::new().hash_one(attempt) as f64 / u64::MAX as f64;
+        Some(backoff.mul_f64(0.5 + random / 2.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(message: &str) -> FfiError {
+        FfiError::Error {
+            code: 1,
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            max_backoff: Duration::from_secs(1),
+            jitter: false,
+            ..Default::default()
+        };
+        let transient = error("error sending request for url");
+
+        let delays: Vec<_> = (1..=5).map(|n| policy.backoff(n, &transient)).collect();
+        assert_eq!(
+            delays,
+            [
+                Some(Duration::from_millis(250)),
+                Some(Duration::from_millis(500)),
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(1)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn only_retries_transient_errors() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.backoff(1, &error("invalid secret reference")), None);
+        assert_eq!(
+            policy.backoff(1, &error(r#"{"name":"RateLimitExceeded","retryAfter":3}"#)),
+            Some(Duration::from_secs(3))
+        );
+
+        let too_long = error(r#"{"name":"RateLimitExceeded","retryAfter":3600}"#);
+        assert_eq!(policy.backoff(1, &too_long), None);
+
+        for retry_after in ["-1", "NaN", "1e30"] {
+            let error = error(&format!(
+                r#"{{"name":"RateLimitExceeded","retryAfter":{retry_after}}}"#
+            ));
+            let backoff = policy.backoff(1, &error).expect("should retry");
+            assert!(backoff <= policy.max_backoff, "{retry_after}: {backoff:?}");
+        }
+
+        let jittered = policy
+            .backoff(1, &error("connection reset"))
+            .expect("should retry");
+        assert!(jittered >= policy.initial_backoff / 2 && jittered <= policy.initial_backoff);
+    }
+}
@@ -0,0 +1,149 @@
+use core::ops::Deref;
+
+use crate::types::Item;
+
+/// How many vaults the async clients list at once while searching.
+pub const CONCURRENCY: usize = 8;
+
+const TITLE_EXACT: u32 = 100;
+const TITLE_PREFIX: u32 = 50;
+const TITLE_WORD: u32 = 10;
+const TITLE_CONTAINS: u32 = 6;
+const TAG_EXACT: u32 = 8;
+const TAG_CONTAINS: u32 = 4;
+const CATEGORY: u32 = 5;
+const WEBSITE: u32 = 3;
+
+/// Scores how well `item` matches `query`, or `None` if it doesn't match at all.
+///
+/// The query is split on whitespace and every term has to match the title, a tag, a website
+/// or the category (case-insensitively). Title matches weigh the most, and an item whose title
+/// equals or starts with the whole query gets a bonus on top.
+pub fn relevance(item: &Item, query: &str) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+
+    let title = item.title.to_lowercase();
+    let category = item.category.to_lowercase();
+    let tags: Vec<String> = item.tags.iter().map(|t| t.to_lowercase()).collect();
+    let websites: Vec<String> = item.websites.iter().map(|w| w.url.to_lowercase()).collect();
+
+    let mut score = 0;
+
+    for term in query.split_whitespace() {
+        let title_score = if title.split_whitespace().any(|word| word.starts_with(term)) {
+            TITLE_WORD
+        } else if title.contains(term) {
+            TITLE_CONTAINS
+        } else {
+            0
+        };
+
+        let tag_score = tags
+            .iter()
+            .map(|tag| match tag {
+                tag if tag == term => TAG_EXACT,
+                tag if tag.contains(term) => TAG_CONTAINS,
+                _ => 0,
+            })
+            .max()
+            .unwrap_or_default();
+
+        let category_score = if category.contains(term) { CATEGORY } else { 0 };
+        let website_score = if websites.iter().any(|url| url.contains(term)) {
+            WEBSITE
+        } else {
+            0
+        };
+
+        let term_score = title_score
+            .max(tag_score)
+            .max(category_score)
+            .max(website_score);
+
+        if term_score == 0 {
+            return None;
+        }
+
+        score += term_score;
+    }
+
+    if title == query {
+        score += TITLE_EXACT;
+    } else if title.starts_with(&query) {
+        score += TITLE_PREFIX;
+    }
+
+    Some(score)
+}
+
+/// Keeps the items matching `query`, ordered from most to least relevant.
+///
+/// Items with the same relevance are ordered by title.
+pub fn rank<T: Deref<Target = Item>>(items: impl IntoIterator<Item = T>, query: &str) -> Vec<T> {
+    let mut scored: Vec<(u32, T)> = items
+        .into_iter()
+        .filter_map(|item| Some((relevance(&item, query)?, item)))
+        .collect();
+
+    scored.sort_by(|(a_score, a), (b_score, b)| {
+        b_score.cmp(a_score).then_with(|| a.title.cmp(&b.title))
+    });
+
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Website;
+
+    fn item(title: &str, category: &str, tags: &[&str], urls: &[&str]) -> Box<Item> {
+        Box::new(Item {
+            id: title.to_owned(),
+            title: title.to_owned(),
+            category: category.to_owned(),
+            websites: urls
+                .iter()
+                .map(|url| Website {
+                    url: (*url).to_owned(),
+                })
+                .collect(),
+            tags: tags.iter().map(|tag| (*tag).to_owned()).collect(),
+        })
+    }
+
+    #[test]
+    fn every_term_must_match() {
+        let postgres = item("Staging Postgres", "Login", &["db"], &[]);
+
+        assert!(relevance(&postgres, "staging postgres login").is_some());
+        assert!(relevance(&postgres, "db staging").is_some());
+        assert!(relevance(&postgres, "production postgres").is_none());
+        assert!(relevance(&postgres, "   ").is_none());
+    }
+
+    #[test]
+    fn ranks_title_matches_first() {
+        let items = vec![
+            item(
+                "Grafana",
+                "Login",
+                &["staging"],
+                &["https://grafana.staging.example.com"],
+            ),
+            item("Staging Postgres", "Database", &[], &[]),
+            item("Staging", "Login", &[], &[]),
+            item("Prod Postgres", "Database", &[], &[]),
+        ];
+
+        let titles: Vec<String> = rank(items, "staging")
+            .into_iter()
+            .map(|item| item.title.clone())
+            .collect();
+
+        assert_eq!(titles, ["Staging", "Staging Postgres", "Grafana"]);
+    }
+}
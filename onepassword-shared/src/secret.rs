@@ -0,0 +1,62 @@
+//! Decoding of secrets returned by the SDK without leaving copies of them in ordinary memory.
+
+use std::fmt;
+
+use onepassword_sys::RustBuffer;
+use secrecy::{SecretString, zeroize::Zeroizing};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+
+/// Decodes a response holding a secret, then wipes and frees the response.
+///
+/// The secret is copied straight from the response into the [`SecretString`]. The exception is
+/// secrets containing JSON escapes, which `serde_json` unescapes in a scratch buffer it doesn't
+/// wipe.
+pub fn decode(response: RustBuffer) -> serde_json::Result<SecretString> {
+    let secret = serde_json::from_slice::<Secret>(response.as_ref());
+    response.zeroize_and_free();
+    secret.map(|secret| secret.0)
+}
+
+struct Secret(SecretString);
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(SecretVisitor)
+    }
+}
+
+struct SecretVisitor;
+
+impl Visitor<'_> for SecretVisitor {
+    type Value = Secret;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a secret string")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Secret, E> {
+        Ok(Secret(SecretString::from(value)))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Secret, E> {
+        let value = Zeroizing::new(value);
+        Ok(Secret(SecretString::from(value.as_str())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn decodes_plain_and_escaped_secrets() {
+        let secret = serde_json::from_slice::<Secret>(br#""hunter2""#).unwrap();
+        assert_eq!(secret.0.expose_secret(), "hunter2");
+
+        let secret = serde_json::from_slice::<Secret>(br#""a\"b\\c\u00e9""#).unwrap();
+        assert_eq!(secret.0.expose_secret(), "a\"b\\cé");
+
+        assert!(serde_json::from_slice::<Secret>(b"42").is_err());
+    }
+}
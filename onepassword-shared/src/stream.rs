@@ -0,0 +1,144 @@
+use core::marker::PhantomData;
+
+use serde::de::{DeserializeOwned, Error};
+
+enum Position {
+    Start,
+    /// Right after `[` or `,`.
+    Element,
+    /// Right after an element.
+    Separator,
+    Done,
+}
+
+/// Decodes the elements of the JSON array in `buffer` one at a time, so only the current one is
+/// held decoded and the first is available without decoding the rest.
+///
+/// After the first error, e.g. from a malformed element, the iterator is exhausted.
+pub struct JsonArray<T, B> {
+    buffer: B,
+    offset: usize,
+    position: Position,
+    element: PhantomData<fn() -> T>,
+}
+
+impl<T, B: AsRef<[u8]>> JsonArray<T, B> {
+    pub fn new(buffer: B) -> Self {
+        Self {
+            buffer,
+            offset: 0,
+            position: Position::Start,
+            element: PhantomData,
+        }
+    }
+
+    /// Skips whitespace and returns the next byte, without consuming it.
+    fn peek(&mut self) -> Option<u8> {
+        let bytes = self.buffer.as_ref();
+        while let Some(&byte) = bytes.get(self.offset) {
+            if !byte.is_ascii_whitespace() {
+                return Some(byte);
+            }
+            self.offset += 1;
+        }
+        None
+    }
+
+    /// Consumes `expected`, or fails with what was there instead.
+    fn expect(&mut self, expected: &[u8]) -> serde_json::Result<u8> {
+        match self.peek() {
+            Some(byte) if expected.contains(&byte) => {
+                self.offset += 1;
+                Ok(byte)
+            }
+            Some(byte) => Err(serde_json::Error::custom(format_args!(
+                "expected one of `{}` at byte {}, found `{}`",
+                expected.escape_ascii(),
+                self.offset,
+                byte.escape_ascii()
+            ))),
+            None => Err(serde_json::Error::custom("unexpected end of the array")),
+        }
+    }
+}
+
+impl<T: DeserializeOwned, B: AsRef<[u8]>> JsonArray<T, B> {
+    fn advance(&mut self) -> serde_json::Result<Option<T>> {
+        loop {
+            match self.position {
+                Position::Start => {
+                    self.expect(b"[")?;
+                    if self.peek() == Some(b']') {
+                        self.offset += 1;
+                        self.position = Position::Done;
+                    } else {
+                        self.position = Position::Element;
+                    }
+                }
+                Position::Separator => match self.expect(b",]")? {
+                    b',' => self.position = Position::Element,
+                    _ => self.position = Position::Done,
+                },
+                Position::Element => {
+                    let bytes = &self.buffer.as_ref()[self.offset..];
+                    let mut elements = serde_json::Deserializer::from_slice(bytes).into_iter();
+
+                    let element = elements
+                        .next()
+                        .unwrap_or_else(|| Err(Error::custom("unexpected end of the array")))?;
+                    self.offset += elements.byte_offset();
+                    self.position = Position::Separator;
+
+                    return Ok(Some(element));
+                }
+                Position::Done => return Ok(None),
+            }
+        }
+    }
+}
+
+impl<T: DeserializeOwned, B: AsRef<[u8]>> Iterator for JsonArray<T, B> {
+    type Item = serde_json::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.advance()
+            .inspect_err(|_| self.position = Position::Done)
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(json: &str) -> Vec<serde_json::Result<u32>> {
+        JsonArray::new(json.as_bytes()).collect()
+    }
+
+    #[test]
+    fn decodes_elements_one_at_a_time() {
+        let elements = decode(" [ 1,2 ,\n3 ] ");
+        assert_eq!(
+            elements.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert!(decode("[]").is_empty());
+
+        let mut objects = JsonArray::<serde_json::Value, _>::new(br#"[{"id":"a"}, {"id":"b"}]"#);
+        assert_eq!(objects.next().unwrap().unwrap()["id"], "a");
+        assert_eq!(objects.next().unwrap().unwrap()["id"], "b");
+        assert!(objects.next().is_none());
+    }
+
+    #[test]
+    fn stops_at_the_first_malformed_element() {
+        let elements = decode("[1, 2, x, 4]");
+        assert_eq!(elements.len(), 3);
+        assert!(elements[..2].iter().all(Result::is_ok));
+        assert!(elements[2].is_err());
+
+        assert_eq!(decode(r#"{"id":"a"}"#).len(), 1);
+        assert!(decode("[1, 2").last().unwrap().is_err());
+        assert!(decode("[1 2]").last().unwrap().is_err());
+    }
+}
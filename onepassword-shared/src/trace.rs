@@ -0,0 +1,45 @@
+//! Spans for the `tracing` feature.
+//!
+//! Only names, ids, sizes and timings are recorded: never secret values, and never the client
+//! config, since it contains the service account token.
+
+use std::time::Instant;
+
+use onepassword_sys::Error as FfiError;
+use tracing::{Span, field::Empty};
+
+use crate::types::{ClientConfig, InvocationParameters};
+
+pub fn client_span(config: &ClientConfig) -> Span {
+    tracing::info_span!(
+        "onepassword.client_new",
+        integration_name = &*config.integration_name,
+        integration_version = &*config.integration_version,
+        latency_ms = Empty,
+        error_kind = Empty,
+    )
+}
+
+/// Covers one invocation, retries included.
+pub fn invocation_span(parameters: &InvocationParameters) -> Span {
+    tracing::debug_span!(
+        "onepassword.invoke",
+        invocation = parameters.name(),
+        vault_id = parameters.vault_id(),
+        item_id = parameters.item_id(),
+        attempts = Empty,
+        request_bytes = Empty,
+        response_bytes = Empty,
+        latency_ms = Empty,
+        error_kind = Empty,
+    )
+}
+
+pub fn record_outcome<T>(span: &Span, started: Instant, result: &Result<T, FfiError>) {
+    span.record("latency_ms", started.elapsed().as_millis() as u64);
+
+    if let Err(e) = result {
+        span.record("error_kind", tracing::field::debug(e.kind()));
+        tracing::debug!(parent: span, code = e.code(), error_kind = ?e.kind(), "call failed");
+    }
+}
@@ -1,31 +1,147 @@
-#[derive(Debug, serde::Serialize)]
+use std::{borrow::Cow, fmt::Debug, path::PathBuf, sync::Arc, time::Duration};
+
+use onepassword_sys::RustBuffer;
+use secrecy::{ExposeSecret, SecretString, zeroize::Zeroizing};
+use serde::ser::SerializeMap;
+
+use crate::{metrics::MetricsSink, platform, resolver::SecretReference, retry::RetryPolicy};
+
+#[derive(serde::Serialize)]
 #[serde(default, rename_all = "camelCase")]
 pub struct ClientConfig {
-    pub service_account_token: String,
-    pub integration_name: &'static str,
-    pub integration_version: &'static str,
+    #[serde(flatten)]
+    pub auth: Auth,
+    pub integration_name: Cow<'static, str>,
+    pub integration_version: Cow<'static, str>,
     pub sdk_version: &'static str,
+    /// The HTTP requests are made by the SDK core rather than this crate, so this describes the
+    /// HTTP library the core uses.
     pub request_library_name: &'static str,
     pub request_library_version: &'static str,
-    pub os: &'static str,
-    pub os_version: &'static str,
-    pub architecture: &'static str,
+    /// Detected at runtime by default, see [`platform`].
+    pub os: Cow<'static, str>,
+    pub os_version: Cow<'static, str>,
+    pub architecture: Cow<'static, str>,
     pub programming_language: &'static str,
+    /// How long listed vault and item names are reused for looking up ids.
+    #[serde(skip)]
+    pub name_cache_ttl: Duration,
+    #[serde(skip)]
+    pub retry_policy: RetryPolicy,
+    /// How long each call to the SDK, including creating the client, may take. Can be
+    /// overridden per client handle with `Client::with_timeout`. Calls that time out aren't
+    /// retried.
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
+    /// Where call and cache statistics are reported to.
+    #[serde(skip)]
+    pub metrics: Option<Arc<dyn MetricsSink>>,
+    /// The desktop app's integration socket, checked before connecting with
+    /// [`Auth::DesktopApp`]. If it's not set, the SDK finds the app itself.
+    #[serde(skip)]
+    pub desktop_app_socket: Option<PathBuf>,
+}
+
+/// How the client authenticates with 1Password.
+#[derive(Debug, Clone)]
+pub enum Auth {
+    ServiceAccount(SecretString),
+    /// Signs in through the 1Password desktop app, which asks the user to approve the client,
+    /// e.g. with biometrics. Meant for interactive use on a developer's machine.
+    DesktopApp {
+        /// The account's name or id, as shown in the desktop app.
+        account_name: String,
+    },
+}
+
+impl Default for Auth {
+    fn default() -> Self {
+        Self::ServiceAccount(SecretString::default())
+    }
+}
+
+impl serde::Serialize for Auth {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        match self {
+            Self::ServiceAccount(token) => {
+                map.serialize_entry("serviceAccountToken", token.expose_secret())?
+            }
+            Self::DesktopApp { account_name } => {
+                map.serialize_entry("accountName", account_name)?
+            }
+        }
+        map.end()
+    }
+}
+
+impl ClientConfig {
+    /// Serializes the config for `init_client`, into a buffer that's wiped when dropped since it
+    /// contains the token.
+    pub fn to_json(&self) -> Zeroizing<Vec<u8>> {
+        // Sized up front, so that growing the buffer doesn't leave copies of the token behind.
+        let mut counter = ByteCounter(0);
+        serde_json::to_writer(&mut counter, self).expect("config should serialize");
+
+        let mut json = Zeroizing::new(Vec::with_capacity(counter.0));
+        serde_json::to_writer(&mut *json, self).expect("config should serialize");
+        json
+    }
+}
+
+struct ByteCounter(usize);
+
+impl std::io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Debug for ClientConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientConfig")
+            .field("auth", &self.auth)
+            .field("integration_name", &self.integration_name)
+            .field("integration_version", &self.integration_version)
+            .field("sdk_version", &self.sdk_version)
+            .field("request_library_name", &self.request_library_name)
+            .field("request_library_version", &self.request_library_version)
+            .field("os", &self.os)
+            .field("os_version", &self.os_version)
+            .field("architecture", &self.architecture)
+            .field("programming_language", &self.programming_language)
+            .field("name_cache_ttl", &self.name_cache_ttl)
+            .field("retry_policy", &self.retry_policy)
+            .field("timeout", &self.timeout)
+            .field("metrics", &self.metrics.is_some())
+            .field("desktop_app_socket", &self.desktop_app_socket)
+            .finish()
+    }
 }
 
 impl Default for ClientConfig {
     fn default() -> Self {
         Self {
-            service_account_token: Default::default(),
-            integration_name: option_env!("CARGO_PKG_NAME").unwrap_or_default(),
-            integration_version: option_env!("CARGO_PKG_VERSION").unwrap_or_default(),
+            auth: Auth::default(),
+            integration_name: option_env!("CARGO_PKG_NAME").unwrap_or_default().into(),
+            integration_version: option_env!("CARGO_PKG_VERSION").unwrap_or_default().into(),
             sdk_version: "0030101",
-            os: "windows",
-            os_version: "0.0.0",
-            architecture: "x86_64",
+            os: platform::os().into(),
+            os_version: platform::os_version().map_or(Cow::Borrowed("0.0.0"), Cow::Owned),
+            architecture: platform::architecture().into(),
             programming_language: "Rust",
             request_library_name: "reqwest",
             request_library_version: "0.11.24",
+            name_cache_ttl: Duration::from_secs(60),
+            retry_policy: RetryPolicy::default(),
+            timeout: None,
+            metrics: None,
+            desktop_app_socket: None,
         }
     }
 }
@@ -36,14 +152,26 @@ pub struct InvocationWrapper {
     pub invocation: Invocation,
 }
 
-#[derive(Debug, serde::Serialize)]
+impl InvocationWrapper {
+    /// Serializes the invocation straight into a buffer for the SDK, sized to fit it without
+    /// growing in the common case.
+    pub fn to_buffer(&self) -> RustBuffer {
+        let hint = self.invocation.parameters.payload_len_hint();
+        let mut buffer = RustBuffer::with_capacity(u32::try_from(hint).unwrap_or(u32::MAX));
+
+        serde_json::to_writer(&mut buffer, self).expect("invocations always serialize");
+        buffer
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
 #[serde(default, rename_all = "camelCase")]
 pub struct Invocation {
     pub client_id: u64,
     pub parameters: InvocationParameters,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize)]
 #[serde(tag = "name", content = "parameters")]
 pub enum InvocationParameters {
     VaultsList {
@@ -58,21 +186,174 @@ pub enum InvocationParameters {
     },
 }
 
-#[derive(Debug, serde::Deserialize)]
+impl InvocationParameters {
+    /// About how long the serialized invocation is, erring on the long side unless strings need
+    /// escaping.
+    fn payload_len_hint(&self) -> usize {
+        // The wrapping objects, the field names and the client id.
+        const ENVELOPE: usize = 128;
+
+        ENVELOPE
+            + match self {
+                Self::VaultsList { .. } => 0,
+                Self::ItemsList { vault_id, filters } => {
+                    vault_id.len() + filters.iter().map(|f| f.len() + 3).sum::<usize>()
+                }
+                Self::SecretsResolve { secret_reference } => secret_reference.len(),
+            }
+    }
+
+    /// The name the SDK knows the invocation by.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::VaultsList { .. } => "VaultsList",
+            Self::ItemsList { .. } => "ItemsList",
+            Self::SecretsResolve { .. } => "SecretsResolve",
+        }
+    }
+
+    /// The vault the invocation is about, which is a title rather than an id for secret
+    /// references using titles.
+    pub fn vault_id(&self) -> Option<&str> {
+        match self {
+            Self::VaultsList { .. } => None,
+            Self::ItemsList { vault_id, .. } => Some(vault_id),
+            Self::SecretsResolve { secret_reference } => {
+                SecretReference::parse(secret_reference).map(|r| r.vault)
+            }
+        }
+    }
+
+    /// The item the invocation is about, see [`InvocationParameters::vault_id`].
+    pub fn item_id(&self) -> Option<&str> {
+        match self {
+            Self::VaultsList { .. } | Self::ItemsList { .. } => None,
+            Self::SecretsResolve { secret_reference } => {
+                SecretReference::parse(secret_reference).map(|r| r.item)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
 pub struct Vault {
     pub id: String,
     pub title: String,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 pub struct Item {
     pub id: String,
     pub title: String,
     pub category: String,
     pub websites: Vec<Website>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 pub struct Website {
     pub url: String,
 }
+
+/// A [`Vault`] borrowing its strings from the response, except those with escapes in them, see
+/// [`Response`](crate::response::Response).
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct VaultView<'a> {
+    #[serde(borrow)]
+    pub id: Cow<'a, str>,
+    #[serde(borrow)]
+    pub title: Cow<'a, str>,
+}
+
+/// An [`Item`] borrowing from the response like [`VaultView`].
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ItemView<'a> {
+    #[serde(borrow)]
+    pub id: Cow<'a, str>,
+    #[serde(borrow)]
+    pub title: Cow<'a, str>,
+    #[serde(borrow)]
+    pub category: Cow<'a, str>,
+    #[serde(borrow)]
+    pub websites: Vec<WebsiteView<'a>>,
+    #[serde(borrow, default, deserialize_with = "borrowed_strs")]
+    pub tags: Vec<Cow<'a, str>>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct WebsiteView<'a> {
+    #[serde(borrow)]
+    pub url: Cow<'a, str>,
+}
+
+/// Serde only borrows `Cow`s that are fields themselves, not elements of a `Vec`.
+fn borrowed_strs<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Cow<'de, str>>, D::Error> {
+    #[derive(serde::Deserialize)]
+    struct Str<'a>(#[serde(borrow)] Cow<'a, str>);
+
+    let strs: Vec<Str> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(strs.into_iter().map(|s| s.0).collect())
+}
+
+impl From<VaultView<'_>> for Vault {
+    fn from(view: VaultView<'_>) -> Self {
+        Self {
+            id: view.id.into_owned(),
+            title: view.title.into_owned(),
+        }
+    }
+}
+
+impl From<ItemView<'_>> for Item {
+    fn from(view: ItemView<'_>) -> Self {
+        Self {
+            id: view.id.into_owned(),
+            title: view.title.into_owned(),
+            category: view.category.into_owned(),
+            websites: view
+                .websites
+                .into_iter()
+                .map(|website| Website {
+                    url: website.url.into_owned(),
+                })
+                .collect(),
+            tags: view.tags.into_iter().map(Cow::into_owned).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_invocations_into_a_buffer() {
+        let parameters = [
+            InvocationParameters::VaultsList { _marker: () },
+            InvocationParameters::ItemsList {
+                vault_id: "a".repeat(26),
+                filters: vec!["archived".into()],
+            },
+            InvocationParameters::SecretsResolve {
+                secret_reference: format!("op://{}/{}/password", "a".repeat(26), "b".repeat(26)),
+            },
+        ];
+
+        for parameters in parameters {
+            let hint = parameters.payload_len_hint();
+            let wrapper = InvocationWrapper {
+                invocation: Invocation {
+                    client_id: u64::MAX,
+                    parameters,
+                },
+            };
+            let json = serde_json::to_string(&wrapper).unwrap();
+
+            assert_eq!(wrapper.to_buffer().as_ref(), json.as_bytes());
+            assert!(hint >= json.len(), "{hint} < {}: {json}", json.len());
+        }
+    }
+}
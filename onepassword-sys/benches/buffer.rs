@@ -0,0 +1,44 @@
+use std::{hint::black_box, io::Write};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use onepassword_sys::RustBuffer;
+
+/// Writing a payload in chunks, like `serde_json::to_writer` does, into a buffer that starts
+/// out empty.
+fn chunked_writes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunked_writes");
+    let payload = vec![b'x'; 1 << 20];
+
+    for chunk_size in [16, 256, 4096] {
+        group.throughput(Throughput::Bytes(payload.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(chunk_size),
+            &chunk_size,
+            |b, &chunk_size| {
+                b.iter(|| {
+                    let mut buffer = RustBuffer::with_capacity(0);
+                    for chunk in payload.chunks(chunk_size) {
+                        buffer.write_all(black_box(chunk)).unwrap();
+                    }
+                    buffer
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn single_write(c: &mut Criterion) {
+    let payload = vec![b'x'; 1 << 20];
+
+    let mut group = c.benchmark_group("single_write");
+    group.throughput(Throughput::Bytes(payload.len() as u64));
+    group.bench_function("1MiB", |b| {
+        b.iter(|| RustBuffer::from(black_box(payload.as_slice())))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, chunked_writes, single_write);
+criterion_main!(benches);
@@ -48,6 +48,11 @@ pub struct RustBuffer {
     data: Option<NonNull<ffi::c_char>>,
 }
 
+// SAFETY: The buffer owns its allocation, which the SDK's allocator can free from any thread, and
+// shared references only read from it.
+unsafe impl Send for RustBuffer {}
+unsafe impl Sync for RustBuffer {}
+
 impl Display for RustBuffer {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         if self.data.is_none() {
@@ -74,28 +79,76 @@ impl RustBuffer {
         *self = new_buf;
     }
 
+    /// Overwrites the whole allocation with zeroes before freeing it, for buffers holding
+    /// secrets.
+    pub fn zeroize_and_free(self) {
+        if let Some(data) = self.data {
+            for i in 0..self.capacity as usize {
+                // Volatile, so the writes aren't optimized away as dead stores before the free.
+                unsafe { data.add(i).write_volatile(0) };
+            }
+            core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    /// Appends `buf`, growing the buffer if needed.
+    ///
+    /// # Panics
+    ///
+    /// If the buffer would become longer than `u32::MAX` bytes.
     pub fn write(&mut self, buf: &[u8]) -> usize {
-        if self.len + (buf.len() as u32) > self.capacity {
-            self.reserve(buf.len() as u32);
+        self.append(buf)
+            .expect("a RustBuffer can't hold more than u32::MAX bytes");
+        buf.len()
+    }
+
+    /// Appends `buf`, at least doubling the capacity if it doesn't fit so that many small writes
+    /// don't reallocate every time. Fails if the length wouldn't fit in a `u32`.
+    fn append(&mut self, buf: &[u8]) -> Result<(), LengthOverflow> {
+        if buf.is_empty() {
+            return Ok(());
         }
 
-        let current_len = self.len as usize;
-        let pointer = self.data.expect("buffer should not be empty");
+        let additional = u32::try_from(buf.len()).map_err(|_| LengthOverflow)?;
+        let new_len = self.len.checked_add(additional).ok_or(LengthOverflow)?;
 
-        for (i, byte) in buf.iter().copied().enumerate() {
-            unsafe { pointer.add(current_len + i).write(byte as _) };
+        if new_len > self.capacity {
+            // The SDK reserves room for `additional` more bytes past the length.
+            let growth = additional.max(self.capacity).min(u32::MAX - self.len);
+            self.reserve(growth);
         }
 
-        self.len = (current_len + buf.len()) as u32;
-        buf.len()
+        let pointer = self.data.expect("buffer should not be empty");
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                buf.as_ptr(),
+                pointer.as_ptr().add(self.len as usize).cast(),
+                buf.len(),
+            );
+        }
+
+        self.len = new_len;
+        Ok(())
+    }
+}
+
+/// Appending would make a [`RustBuffer`] longer than `u32::MAX` bytes.
+#[derive(Debug)]
+struct LengthOverflow;
+
+impl From<&[u8]> for RustBuffer {
+    fn from(value: &[u8]) -> Self {
+        let capacity =
+            u32::try_from(value.len()).expect("a RustBuffer can't hold more than u32::MAX bytes");
+        let mut buffer = Self::with_capacity(capacity);
+        buffer.write(value);
+        buffer
     }
 }
 
 impl From<&str> for RustBuffer {
     fn from(value: &str) -> Self {
-        let mut buffer = Self::with_capacity(value.len() as _);
-        buffer.write(value.as_bytes());
-        buffer
+        value.as_bytes().into()
     }
 }
 
@@ -129,22 +182,56 @@ impl AsMut<[u8]> for RustBuffer {
 #[cfg(feature = "std")]
 impl std::io::Write for RustBuffer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        if self.len + (buf.len() as u32) > self.capacity {
-            self.reserve(buf.len() as u32);
-        }
+        self.append(buf).map_err(|LengthOverflow| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "a RustBuffer can't hold more than u32::MAX bytes",
+            )
+        })?;
+        Ok(buf.len())
+    }
 
-        let current_len = self.len as usize;
-        let pointer = self.data.expect("buffer should not be empty");
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for (i, byte) in buf.iter().copied().enumerate() {
-            unsafe { pointer.add(current_len + i).write(byte as _) };
+    #[test]
+    fn grows_geometrically() {
+        let mut buffer = RustBuffer::with_capacity(0);
+        let mut reallocations = 0;
+
+        for i in 0..1000u32 {
+            let capacity = buffer.capacity;
+            buffer.write(&i.to_le_bytes());
+            reallocations += usize::from(buffer.capacity != capacity);
         }
 
-        self.len = (current_len + buf.len()) as u32;
-        Ok(buf.len())
+        assert!(reallocations <= 13, "{reallocations} reallocations");
+        assert_eq!(buffer.len, 4000);
+        assert!(
+            buffer
+                .as_ref()
+                .chunks(4)
+                .enumerate()
+                .all(|(i, chunk)| chunk == (i as u32).to_le_bytes())
+        );
     }
 
-    fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+    #[test]
+    fn refuses_to_overflow_the_length() {
+        let mut buffer = RustBuffer {
+            capacity: u32::MAX,
+            len: u32::MAX - 1,
+            data: None,
+        };
+
+        assert!(std::io::Write::write(&mut buffer, b"ab").is_err());
+        assert_eq!(buffer.len, u32::MAX - 1);
+        assert_eq!(buffer.write(b""), 0);
     }
 }
@@ -0,0 +1,295 @@
+//! Lets the SDK call into Rust through uniffi callback interfaces, e.g. for HTTP transport or
+//! progress reporting.
+//!
+//! The SDK is given a vtable of `extern "C"` trampolines once, and a handle for every object lent
+//! to it, which the trampolines look up in a [`HandleMap`] to find the trait object to call.
+
+use core::{
+    any::Any,
+    panic::AssertUnwindSafe,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use std::{
+    collections::BTreeMap,
+    panic,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    buffer::RustBuffer,
+    errors::{CallStatus, CallStatusCode},
+};
+
+/// Keeps the objects lent to the SDK alive until it frees their handles.
+///
+/// Handles are odd, which uniffi uses to tell foreign handles apart from its own.
+pub(crate) struct HandleMap<T: ?Sized> {
+    next: AtomicU64,
+    objects: Mutex<BTreeMap<u64, Arc<T>>>,
+}
+
+impl<T: ?Sized> HandleMap<T> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            next: AtomicU64::new(1),
+            objects: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub(crate) fn insert(&self, object: Arc<T>) -> u64 {
+        let handle = self.next.fetch_add(2, Ordering::Relaxed);
+        self.objects.lock().unwrap().insert(handle, object);
+        handle
+    }
+
+    pub(crate) fn get(&self, handle: u64) -> Option<Arc<T>> {
+        self.objects.lock().unwrap().get(&handle).cloned()
+    }
+
+    pub(crate) fn remove(&self, handle: u64) -> Option<Arc<T>> {
+        self.objects.lock().unwrap().remove(&handle)
+    }
+}
+
+/// Calls `method` on the object behind `handle` for the SDK, writing what it returns to
+/// `out_return`, and reporting errors and panics through `call_status` since unwinding into the
+/// SDK would abort.
+///
+/// # Safety
+///
+/// `out_return` and `call_status` must be valid for writes.
+pub(crate) unsafe fn call<T: ?Sized, R>(
+    handles: &HandleMap<T>,
+    handle: u64,
+    out_return: *mut R,
+    call_status: *mut CallStatus,
+    method: impl FnOnce(&T) -> Result<R, RustBuffer>,
+) {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let object = handles
+            .get(handle)
+            .unwrap_or_else(|| panic!("unknown callback handle {handle}"));
+        method(&object)
+    }));
+
+    let call_status = unsafe { &mut *call_status };
+    match result {
+        Ok(Ok(value)) => unsafe { out_return.write(value) },
+        Ok(Err(error)) => {
+            call_status.code = CallStatusCode::Error;
+            call_status.error_buf = error;
+        }
+        Err(payload) => {
+            call_status.code = CallStatusCode::Panic;
+            call_status.error_buf = RustBuffer::from(panic_message(&*payload));
+        }
+    }
+}
+
+/// Releases the SDK's handle, dropping the object unless it's still used elsewhere.
+pub(crate) fn free<T: ?Sized>(handles: &HandleMap<T>, handle: u64) {
+    // There's no way to report a panicking destructor to the SDK.
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(handles.remove(handle))));
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("Unknown rust panic", String::as_str),
+    }
+}
+
+/// Declares a trait the SDK can call through the `<name>` callback interface, along with a
+/// module of the same name holding its vtable and `lower`, which lends an object to the SDK.
+///
+/// Every method returns its value or an error the SDK lifts from the buffer, and the argument
+/// and return types must be the ones uniffi passes for them, e.g. integers or [`RustBuffer`].
+macro_rules! callback_interface {
+    (
+        $name:ident:
+        $(#[$attr:meta])*
+        $vis:vis trait $trait:ident {
+            $(
+                $(#[$method_attr:meta])*
+                fn $method:ident(&self $(, $arg:ident: $ty:ty)* $(,)?) -> Result<$ret:ty, RustBuffer>;
+            )*
+        }
+    ) => {preinterpret! {
+        $(#[$attr])*
+        $vis trait $trait: Send + Sync {
+            $(
+                $(#[$method_attr])*
+                fn $method(&self $(, $arg: $ty)*) -> Result<$ret, $crate::RustBuffer>;
+            )*
+        }
+
+        $vis mod $name {
+            use super::*;
+            use std::sync::{Arc, Once};
+            use $crate::{callbacks::{self, HandleMap}, errors::CallStatus};
+
+            /// Laid out like the vtable uniffi generates for the interface.
+            #[repr(C)]
+            pub struct VTable {
+                $(pub $method: extern "C" fn(u64, $($ty,)* *mut $ret, *mut CallStatus),)*
+                pub uniffi_free: extern "C" fn(u64),
+            }
+
+            static HANDLES: HandleMap<dyn $trait> = HandleMap::new();
+            static VTABLE: VTable = VTable {
+                $($method,)*
+                uniffi_free,
+            };
+
+            $(
+                extern "C" fn $method(
+                    handle: u64,
+                    $($arg: $ty,)*
+                    out_return: *mut $ret,
+                    call_status: *mut CallStatus,
+                ) {
+                    unsafe {
+                        callbacks::call(&HANDLES, handle, out_return, call_status, |object| {
+                            object.$method($($arg),*)
+                        })
+                    }
+                }
+            )*
+
+            extern "C" fn uniffi_free(handle: u64) {
+                callbacks::free(&HANDLES, handle)
+            }
+
+            #[cfg_attr(windows, link(name = "op_uniffi_core", kind = "raw-dylib"))]
+            #[cfg_attr(not(windows), link(name = "op_uniffi_core"))]
+            unsafe extern "C" {
+                #[link_name = [!string! "uniffi_op_uniffi_core_fn_init_callback_vtable_" $name]]
+                unsafe fn init_vtable(vtable: &'static VTable);
+            }
+
+            /// Lends `object` to the SDK, returning the handle to pass it as. The vtable is
+            /// registered the first time.
+            pub(crate) fn lower(object: Arc<dyn $trait>) -> u64 {
+                static REGISTER: Once = Once::new();
+                REGISTER.call_once(|| unsafe { init_vtable(&VTABLE) });
+
+                HANDLES.insert(object)
+            }
+        }
+    }};
+}
+
+pub(crate) use callback_interface;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{
+        ptr,
+        sync::atomic::{AtomicPtr, AtomicU8},
+    };
+    use preinterpret::preinterpret;
+
+    crate::callbacks::callback_interface! {
+        progress:
+        /// Stands in for a callback interface of the SDK.
+        trait Progress {
+            fn report(&self, done: u64, total: u64) -> Result<u8, RustBuffer>;
+            fn describe(&self, message: RustBuffer) -> Result<(), RustBuffer>;
+        }
+    }
+
+    /// The SDK's end of the stand-in, which keeps the vtable like uniffi's scaffolding does.
+    static REGISTERED: AtomicPtr<progress::VTable> = AtomicPtr::new(ptr::null_mut());
+
+    #[unsafe(no_mangle)]
+    extern "C" fn uniffi_op_uniffi_core_fn_init_callback_vtable_progress(
+        vtable: *const progress::VTable,
+    ) {
+        REGISTERED.store(vtable.cast_mut(), Ordering::Release);
+    }
+
+    fn vtable() -> &'static progress::VTable {
+        let vtable = REGISTERED.load(Ordering::Acquire);
+        assert!(!vtable.is_null(), "the vtable wasn't registered");
+        unsafe { &*vtable }
+    }
+
+    fn call_status() -> CallStatus {
+        CallStatus {
+            code: CallStatusCode::Success,
+            error_buf: RustBuffer::default(),
+        }
+    }
+
+    fn report(handle: u64, done: u64, total: u64) -> (CallStatus, u8) {
+        let (mut status, mut percent) = (call_status(), 0);
+        (vtable().report)(handle, done, total, &mut percent, &mut status);
+        (status, percent)
+    }
+
+    struct Reporter {
+        reports: AtomicU8,
+    }
+
+    impl Progress for Reporter {
+        fn report(&self, done: u64, total: u64) -> Result<u8, RustBuffer> {
+            if total == 0 {
+                panic!("nothing to do");
+            }
+
+            self.reports.fetch_add(1, Ordering::Relaxed);
+            Ok((done * 100 / total) as u8)
+        }
+
+        fn describe(&self, message: RustBuffer) -> Result<(), RustBuffer> {
+            match message.len {
+                0 => Err("empty description".into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn calls_rust_objects_through_the_vtable() {
+        let reporter = Arc::new(Reporter {
+            reports: AtomicU8::new(0),
+        });
+        let handle = progress::lower(reporter.clone());
+        assert_eq!(handle % 2, 1);
+
+        let (status, percent) = report(handle, 1, 4);
+        assert!(matches!(status.code, CallStatusCode::Success));
+        assert_eq!(percent, 25);
+        assert_eq!(reporter.reports.load(Ordering::Relaxed), 1);
+
+        let mut status = call_status();
+        (vtable().describe)(handle, RustBuffer::default(), &mut (), &mut status);
+        assert!(matches!(status.code, CallStatusCode::Error));
+        assert_eq!(status.error_buf.to_string(), "empty description");
+
+        (vtable().uniffi_free)(handle);
+        assert_eq!(Arc::strong_count(&reporter), 1);
+    }
+
+    #[test]
+    fn reports_panics_instead_of_unwinding() {
+        let handle = progress::lower(Arc::new(Reporter {
+            reports: AtomicU8::new(0),
+        }));
+
+        let (status, _) = report(handle, 0, 0);
+        assert!(matches!(status.code, CallStatusCode::Panic));
+        assert_eq!(status.error_buf.to_string(), "nothing to do");
+
+        (vtable().uniffi_free)(handle);
+        let (status, _) = report(handle, 1, 2);
+        assert!(matches!(status.code, CallStatusCode::Panic));
+        assert_eq!(
+            status.error_buf.to_string(),
+            format!("unknown callback handle {handle}")
+        );
+    }
+}
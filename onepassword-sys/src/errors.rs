@@ -6,23 +6,145 @@ pub type FfiResult<T = (), E = Error> = Result<T, E>;
 
 #[derive(Debug)]
 pub enum Error {
-    Error { code: i32, message: String },
+    Error {
+        code: i32,
+        message: String,
+    },
+    /// The call didn't finish in time, and was cancelled if the SDK allowed it.
+    Timeout {
+        after: core::time::Duration,
+    },
+}
+
+/// Broad classification of an [`Error`], mostly for deciding whether a call is worth retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The service account hit its rate limit.
+    RateLimited,
+    /// The SDK couldn't reach 1Password.
+    Network,
+    /// The SDK doesn't know the client id anymore, e.g. because its session expired.
+    InvalidClient,
+    /// The desktop app session expired and has to be unlocked again.
+    DesktopSessionExpired,
+    Timeout,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RateLimited => "rate_limited",
+            Self::Network => "network",
+            Self::InvalidClient => "invalid_client",
+            Self::DesktopSessionExpired => "desktop_session_expired",
+            Self::Timeout => "timeout",
+            Self::Other => "other",
+        }
+    }
 }
 
 impl Error {
+    /// The error code reported by the SDK, or -1 for errors that didn't come from it.
     pub fn code(&self) -> i32 {
         match self {
             Self::Error { code, .. } => *code,
+            Self::Timeout { .. } => -1,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Error { message, .. } => message,
+            Self::Timeout { .. } => "invocation timed out",
         }
     }
+
+    pub fn kind(&self) -> ErrorKind {
+        if let Self::Timeout { .. } = self {
+            return ErrorKind::Timeout;
+        }
+
+        // Typed SDK errors come as a JSON object with a `name` and a `message`.
+        match json_field(self.message(), "name") {
+            Some("RateLimitExceeded") => return ErrorKind::RateLimited,
+            Some("DesktopSessionExpired") => return ErrorKind::DesktopSessionExpired,
+            _ => {}
+        }
+
+        let message = self.message().to_ascii_lowercase();
+        if message.contains("invalid client id") {
+            return ErrorKind::InvalidClient;
+        }
+
+        let network_failure = [
+            "error sending request",
+            "connection",
+            "timed out",
+            "dns error",
+        ]
+        .iter()
+        .any(|needle| message.contains(needle));
+
+        if network_failure {
+            ErrorKind::Network
+        } else {
+            ErrorKind::Other
+        }
+    }
+
+    /// Whether the same call might succeed if it's simply made again later.
+    ///
+    /// Timeouts aren't, since retrying them would let a call run for several times its timeout.
+    pub fn is_transient(&self) -> bool {
+        matches!(self.kind(), ErrorKind::RateLimited | ErrorKind::Network)
+    }
+
+    /// Whether the client has to be created again before it can be used, which the clients in
+    /// `onepassword` and `onepassword-async` do automatically.
+    pub fn invalidates_client(&self) -> bool {
+        matches!(
+            self.kind(),
+            ErrorKind::InvalidClient | ErrorKind::DesktopSessionExpired
+        )
+    }
+
+    /// How long the SDK asked us to wait before trying again, if it said so sensibly.
+    pub fn retry_after(&self) -> Option<core::time::Duration> {
+        let seconds: f64 = json_field(self.message(), "retryAfter")?.parse().ok()?;
+        // Negative, infinite or NaN delays are as good as none, and huge ones don't fit.
+        core::time::Duration::try_from_secs_f64(seconds).ok()
+    }
+}
+
+/// Finds the value of `"field": value` in `json`, without the quotes if it's a string.
+///
+/// Good enough for the flat objects the SDK uses for typed errors, without pulling in a JSON
+/// parser just for that.
+fn json_field<'a>(json: &'a str, field: &str) -> Option<&'a str> {
+    let key = format!("\"{field}\"");
+    let (_, rest) = json.split_once(key.as_str())?;
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+
+    match rest.strip_prefix('"') {
+        Some(string) => string.split_once('"').map(|(value, _)| value),
+        None => rest
+            .split(|c: char| c == ',' || c == '}' || c.is_whitespace())
+            .next(),
+    }
 }
 
 #[repr(u8)]
-#[expect(dead_code)]
 pub(crate) enum CallStatusCode {
     Success = 0,
     Error = 1,
     Panic = 2,
+    /// The future was completed before it was ready, or after it was cancelled.
+    #[cfg_attr(
+        not(all(test, feature = "sync")),
+        expect(dead_code, reason = "only ever set by the SDK")
+    )]
+    Cancelled = 3,
 }
 
 #[repr(C)]
@@ -91,5 +213,61 @@ pub(crate) fn check_call_status<C: ErrorConverter>(
             };
             panic!("{msg}");
         }
+        CallStatusCode::Cancelled => panic!("the SDK future was cancelled"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(message: &str) -> Error {
+        Error::Error {
+            code: 1,
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn classifies_typed_errors() {
+        let rate_limited =
+            error(r#"{"name": "RateLimitExceeded", "message": "slow down", "retryAfter": 2.5}"#);
+        assert_eq!(rate_limited.kind(), ErrorKind::RateLimited);
+        assert!(rate_limited.is_transient());
+        assert_eq!(
+            rate_limited.retry_after(),
+            Some(core::time::Duration::from_millis(2500))
+        );
+
+        let network = error("error sending request for url (https://my.1password.com/api)");
+        assert_eq!(network.kind(), ErrorKind::Network);
+
+        let timeout = Error::Timeout {
+            after: core::time::Duration::from_secs(1),
+        };
+        assert_eq!(timeout.kind(), ErrorKind::Timeout);
+        assert!(!timeout.is_transient());
+
+        let not_found = error("no item matched the secret reference query");
+        assert_eq!(not_found.kind(), ErrorKind::Other);
+        assert!(!not_found.is_transient());
+        assert_eq!(not_found.retry_after(), None);
+        assert!(!not_found.invalidates_client());
+
+        for retry_after in ["-1", "NaN", "inf", "1e30"] {
+            let error = error(&format!(
+                r#"{{"name": "RateLimitExceeded", "retryAfter": {retry_after}}}"#
+            ));
+            assert_eq!(error.retry_after(), None, "{retry_after}");
+        }
+
+        let session_expired = error(r#"{"name": "DesktopSessionExpired", "message": "locked"}"#);
+        assert_eq!(session_expired.kind(), ErrorKind::DesktopSessionExpired);
+        assert!(session_expired.invalidates_client());
+
+        let expired = error("invalid client id");
+        assert_eq!(expired.kind(), ErrorKind::InvalidClient);
+        assert!(expired.invalidates_client());
+        assert!(!expired.is_transient());
     }
 }
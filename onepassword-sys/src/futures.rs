@@ -3,9 +3,9 @@ use core::{
     fmt::Debug,
     future::Future,
     marker::PhantomData,
-    ptr::NonNull,
+    pin::Pin,
     sync::atomic::{AtomicU8, Ordering},
-    task::Waker,
+    task::{Context, Poll},
 };
 use preinterpret::preinterpret;
 
@@ -13,6 +13,7 @@ use crate::{
     buffer::RustBuffer,
     errors::{CallStatus, ErrorConverter},
     util::rust_call,
+    waker::AtomicWaker,
 };
 
 #[repr(u8)]
@@ -20,18 +21,23 @@ use crate::{
 pub(crate) enum FuturePollCode {
     Ready = 0,
     MaybeReady = 1,
+    /// Never passed by the SDK, but stored while its continuation hasn't been called yet.
+    Waiting = 2,
 }
 
-#[repr(C)]
+/// Shared with the SDK, which calls [`future_callback`] with it from whichever thread the future
+/// makes progress on, possibly while [`FfiFuture::poll`] registers a new waker. It's kept on the
+/// heap so it stays put when the [`FfiFuture`] is moved between polls.
 pub(crate) struct FutureState {
-    waker_data: *const (),
-    waker_vtable: NonNull<()>,
+    waker: AtomicWaker,
     poll_code: AtomicU8,
 }
 
-type FutureContinuation = extern "C" fn(*mut FutureState, FuturePollCode);
+/// The SDK only passes the state back to the continuation, so it's opaque to it.
+type FutureStatePtr = *const ffi::c_void;
+type FutureContinuation = extern "C" fn(FutureStatePtr, FuturePollCode);
 
-type PollFn<T> = unsafe extern "C" fn(FfiFutureHandle<T>, FutureContinuation, *mut FutureState);
+type PollFn<T> = unsafe extern "C" fn(FfiFutureHandle<T>, FutureContinuation, FutureStatePtr);
 type CancelFn<T> = unsafe extern "C" fn(FfiFutureHandle<T>);
 type FreeFn<T> = unsafe extern "C" fn(FfiFutureHandle<T>);
 type CompleteFn<T> = unsafe extern "C" fn(FfiFutureHandle<T>, *mut CallStatus) -> T;
@@ -51,56 +57,77 @@ pub(crate) trait FfiFutureReturnValue {
         Self: core::marker::Sized;
 }
 
+/// Declares the SDK's `rust_future_*_<name>` functions for each return type, and implements
+/// [`FfiFutureReturnValue`] for it so functions returning an [`FfiFutureHandle`] of it can be
+/// awaited.
 macro_rules! declare_futures {
-    ($($kind:ty),+) => {
+    ($($name:ident: $kind:ty),+ $(,)?) => {
         preinterpret! {
             #[cfg_attr(windows, link(name = "op_uniffi_core", kind = "raw-dylib"))]
             #[cfg_attr(not(windows), link(name = "op_uniffi_core"))]
             unsafe extern "C" {
                 $(
-                    #[link_name = [!snake! "ffi_op_uniffi_core_rust_future_poll_" $kind]]
-                    unsafe fn [!ident_snake! poll_ffi_future_ $kind](
+                    #[link_name = [!string! "ffi_op_uniffi_core_rust_future_poll_" $name]]
+                    unsafe fn [!ident! poll_ffi_future_ $name](
                         future: FfiFutureHandle<$kind>,
                         continuation: FutureContinuation,
-                        state_ptr: *mut FutureState,
+                        state_ptr: FutureStatePtr,
                     );
-                    #[link_name = [!snake! "ffi_op_uniffi_core_rust_future_cancel_" $kind]]
-                    unsafe fn [!ident_snake! cancel_ffi_future_ $kind](future: FfiFutureHandle<$kind>);
-                    #[link_name = [!snake! "ffi_op_uniffi_core_rust_future_complete_" $kind]]
-                    unsafe fn [!ident_snake! complete_ffi_future_ $kind](
+                    #[link_name = [!string! "ffi_op_uniffi_core_rust_future_cancel_" $name]]
+                    unsafe fn [!ident! cancel_ffi_future_ $name](future: FfiFutureHandle<$kind>);
+                    #[link_name = [!string! "ffi_op_uniffi_core_rust_future_complete_" $name]]
+                    unsafe fn [!ident! complete_ffi_future_ $name](
                         future: FfiFutureHandle<$kind>,
                         status: *mut CallStatus,
-                    ) -> RustBuffer;
-                    #[link_name = [!snake! "ffi_op_uniffi_core_rust_future_free_" $kind]]
-                    unsafe fn [!ident_snake! free_ffi_future_ $kind](future: FfiFutureHandle<$kind>);
-                ),+
+                    ) -> $kind;
+                    #[link_name = [!string! "ffi_op_uniffi_core_rust_future_free_" $name]]
+                    unsafe fn [!ident! free_ffi_future_ $name](future: FfiFutureHandle<$kind>);
+                )+
             }
 
             $(
                 impl FfiFutureReturnValue for $kind {
                     fn poll_fn() -> PollFn<Self>
                     where
-                        Self: core::marker::Sized {[!ident_snake! poll_ffi_future_ $kind] }
+                        Self: core::marker::Sized {[!ident! poll_ffi_future_ $name] }
                     fn cancel_fn() -> CancelFn<Self>
                     where
-                        Self: core::marker::Sized {[!ident_snake! cancel_ffi_future_ $kind] }
+                        Self: core::marker::Sized {[!ident! cancel_ffi_future_ $name] }
                     fn free_fn() -> FreeFn<Self>
                     where
-                        Self: core::marker::Sized {[!ident_snake! free_ffi_future_ $kind] }
+                        Self: core::marker::Sized {[!ident! free_ffi_future_ $name] }
                     fn complete_fn() -> CompleteFn<Self>
                     where
-                        Self: core::marker::Sized {[!ident_snake! complete_ffi_future_ $kind] }
+                        Self: core::marker::Sized {[!ident! complete_ffi_future_ $name] }
                 }
-            ),+
+            )+
         }
     };
 }
 
-declare_futures!(RustBuffer);
+declare_futures! {
+    u8: u8,
+    i8: i8,
+    u16: u16,
+    i16: i16,
+    u32: u32,
+    i32: i32,
+    u64: u64,
+    i64: i64,
+    f32: f32,
+    f64: f64,
+    pointer: *const ffi::c_void,
+    rust_buffer: RustBuffer,
+    void: (),
+}
 
 #[repr(transparent)]
 pub(crate) struct FfiFutureHandle<T: FfiFutureReturnValue>(*mut ffi::c_void, PhantomData<T>);
 
+// SAFETY: The handle points to a future owned by the SDK, which uniffi requires to be `Send`, and
+// whose poll, cancel, complete and free functions may be called from any thread.
+unsafe impl<T: FfiFutureReturnValue> Send for FfiFutureHandle<T> {}
+
 impl<T: FfiFutureReturnValue> Copy for FfiFutureHandle<T> {}
 impl<T: FfiFutureReturnValue> Clone for FfiFutureHandle<T> {
     fn clone(&self) -> Self {
@@ -112,72 +139,83 @@ impl<T: FfiFutureReturnValue> FfiFutureHandle<T> {
     pub fn into_future<C: ErrorConverter>(self) -> FfiFuture<T, C> {
         FfiFuture {
             future: self,
-            waker: None,
-            state: FutureState {
-                waker_data: core::ptr::dangling(),
-                waker_vtable: NonNull::dangling(),
+            state: Box::pin(FutureState {
+                waker: AtomicWaker::new(),
+                // The SDK future has to be polled once before it calls a continuation.
                 poll_code: AtomicU8::new(FuturePollCode::MaybeReady as u8),
-            },
+            }),
             is_finished: false,
             converter: PhantomData::<C>,
         }
     }
 }
 
-extern "C" fn future_callback(state: *mut FutureState, code: FuturePollCode) {
-    let FutureState {
-        waker_data,
-        waker_vtable,
-        poll_code,
-    } = unsafe { &*state };
+extern "C" fn future_callback(state: FutureStatePtr, code: FuturePollCode) {
+    // SAFETY: The state outlives the SDK future, which is freed before the state is dropped.
+    let FutureState { waker, poll_code } = unsafe { &*state.cast::<FutureState>() };
     poll_code.store(code as u8, Ordering::Release);
-    unsafe { Waker::new(*waker_data, waker_vtable.cast().as_ref()) }.wake_by_ref();
+    waker.wake();
 }
 
+/// An SDK future, which once it's finished stays [`Poll::Pending`] instead of panicking like most
+/// futures do.
 pub(crate) struct FfiFuture<T: FfiFutureReturnValue, C: ErrorConverter> {
     future: FfiFutureHandle<T>,
-    waker: Option<Waker>,
-    state: FutureState,
+    state: Pin<Box<FutureState>>,
     converter: PhantomData<C>,
     is_finished: bool,
 }
 
-impl<T: FfiFutureReturnValue + Unpin, C: ErrorConverter + Unpin> Future for FfiFuture<T, C> {
+// The state is pinned on its own, and `T` and `C` are only markers.
+impl<T: FfiFutureReturnValue, C: ErrorConverter> Unpin for FfiFuture<T, C> {}
+
+impl<T: FfiFutureReturnValue, C: ErrorConverter> Future for FfiFuture<T, C> {
     type Output = Result<T, C::ErrorType>;
 
-    fn poll(
-        self: core::pin::Pin<&mut Self>,
-        cx: &mut core::task::Context<'_>,
-    ) -> core::task::Poll<Self::Output> {
-        if self.is_finished {
-            panic!("polling finished future");
-        }
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("onepassword_sys.ffi_future_poll").entered();
 
         let Self {
             state,
-            waker,
             future,
             is_finished,
             ..
         } = self.get_mut();
 
-        let waker = waker.get_or_insert_with(|| cx.waker().clone());
-        waker.clone_from(cx.waker());
+        // The output has been taken, and the SDK future can't be completed twice.
+        if *is_finished {
+            return Poll::Pending;
+        }
 
-        state.waker_data = waker.data();
-        state.waker_vtable = NonNull::from(waker.vtable()).cast();
+        state.waker.register(cx.waker());
 
-        if state.poll_code.load(Ordering::Acquire) == (FuturePollCode::Ready as u8) {
-            *is_finished = true;
+        match state.poll_code.load(Ordering::Acquire) {
+            code if code == FuturePollCode::Ready as u8 => {
+                *is_finished = true;
 
-            let complete_fn = T::complete_fn();
-            let output = unsafe { rust_call!(complete_fn -> C, *future) };
+                #[cfg(feature = "tracing")]
+                tracing::trace!("ffi future ready");
 
-            return core::task::Poll::Ready(output);
+                let complete_fn = T::complete_fn();
+                let output = unsafe { rust_call!(complete_fn -> C, *future) };
+
+                return Poll::Ready(output);
+            }
+            // Polling the SDK future again now would make it call the pending continuation with
+            // `Ready` although it isn't, so wait for that continuation to wake the new waker.
+            code if code == FuturePollCode::Waiting as u8 => return Poll::Pending,
+            _ => {}
         }
 
-        unsafe { (T::poll_fn())(*future, future_callback, state) };
-        core::task::Poll::Pending
+        // Stored before polling, since the continuation may be called before the poll returns.
+        state
+            .poll_code
+            .store(FuturePollCode::Waiting as u8, Ordering::Release);
+
+        let state: *const FutureState = &**state;
+        unsafe { (T::poll_fn())(*future, future_callback, state.cast()) };
+        Poll::Pending
     }
 }
 
@@ -190,3 +228,226 @@ impl<T: FfiFutureReturnValue, C: ErrorConverter> Drop for FfiFuture<T, C> {
         unsafe { (T::free_fn())(self.future) }
     }
 }
+
+#[cfg(all(test, feature = "sync", not(loom)))]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::{CallStatusCode, NoConverter},
+        util::block_on_timeout,
+    };
+    use core::{
+        sync::atomic::{AtomicBool, AtomicUsize},
+        task::Waker,
+        time::Duration,
+    };
+    use std::{
+        sync::{Arc, Mutex},
+        thread::{self, JoinHandle},
+    };
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq)]
+    struct Answer(u32);
+
+    /// Stands in for an SDK future, which makes progress on threads of its own like the SDK's
+    /// executor does.
+    ///
+    /// Like uniffi's scheduler, it keeps one continuation at a time, and calls a pending one with
+    /// `Ready` when polled again, even though the future isn't. Completing a future that isn't
+    /// ready reports it as cancelled.
+    #[derive(Default)]
+    struct Simulated {
+        pending_polls: AtomicUsize,
+        ready: AtomicBool,
+        continuation: Mutex<Option<(FutureContinuation, usize)>>,
+        polls: AtomicUsize,
+        completions: AtomicUsize,
+        cancellations: AtomicUsize,
+        freed: AtomicUsize,
+        wakers: Mutex<Vec<JoinHandle<()>>>,
+    }
+
+    impl Simulated {
+        fn start(pending_polls: usize) -> (Arc<Self>, FfiFuture<Answer, NoConverter>) {
+            let simulated = Arc::new(Self {
+                pending_polls: AtomicUsize::new(pending_polls),
+                ..Default::default()
+            });
+            let handle = FfiFutureHandle(
+                Arc::into_raw(simulated.clone()).cast_mut().cast(),
+                PhantomData,
+            );
+
+            (simulated, handle.into_future())
+        }
+
+        fn get(handle: &FfiFutureHandle<Answer>) -> &Self {
+            unsafe { &*handle.0.cast::<Self>() }
+        }
+    }
+
+    unsafe extern "C" fn poll(
+        handle: FfiFutureHandle<Answer>,
+        continuation: FutureContinuation,
+        state: FutureStatePtr,
+    ) {
+        let simulated = Simulated::get(&handle);
+        simulated.polls.fetch_add(1, Ordering::Relaxed);
+
+        let code =
+            match simulated
+                .pending_polls
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            {
+                Ok(_) => FuturePollCode::MaybeReady,
+                Err(_) => FuturePollCode::Ready,
+            };
+
+        let replaced = simulated
+            .continuation
+            .lock()
+            .unwrap()
+            .replace((continuation, state as usize));
+        if let Some((continuation, state)) = replaced {
+            continuation(state as FutureStatePtr, FuturePollCode::Ready);
+        }
+
+        let simulated = unsafe {
+            let simulated = handle.0.cast::<Simulated>().cast_const();
+            Arc::increment_strong_count(simulated);
+            Arc::from_raw(simulated)
+        };
+        let waker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1));
+            simulated
+                .ready
+                .fetch_or(code == FuturePollCode::Ready, Ordering::Release);
+            if let Some((continuation, state)) = simulated.continuation.lock().unwrap().take() {
+                continuation(state as FutureStatePtr, code);
+            }
+        });
+        Simulated::get(&handle).wakers.lock().unwrap().push(waker);
+    }
+
+    unsafe extern "C" fn cancel(handle: FfiFutureHandle<Answer>) {
+        Simulated::get(&handle)
+            .cancellations
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    unsafe extern "C" fn complete(
+        handle: FfiFutureHandle<Answer>,
+        status: *mut CallStatus,
+    ) -> Answer {
+        let simulated = Simulated::get(&handle);
+        if !simulated.ready.load(Ordering::Acquire) {
+            unsafe { (*status).code = CallStatusCode::Cancelled };
+            return Answer(0);
+        }
+
+        Answer(simulated.completions.fetch_add(1, Ordering::Relaxed) as u32 + 42)
+    }
+
+    /// Like the SDK, never calls the continuation once it returns.
+    unsafe extern "C" fn free(handle: FfiFutureHandle<Answer>) {
+        let simulated = unsafe { Arc::from_raw(handle.0.cast::<Simulated>()) };
+
+        for waker in simulated.wakers.lock().unwrap().drain(..) {
+            waker.join().unwrap();
+        }
+        simulated.freed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    impl FfiFutureReturnValue for Answer {
+        fn poll_fn() -> PollFn<Self> {
+            poll
+        }
+        fn cancel_fn() -> CancelFn<Self> {
+            cancel
+        }
+        fn free_fn() -> FreeFn<Self> {
+            free
+        }
+        fn complete_fn() -> CompleteFn<Self> {
+            complete
+        }
+    }
+
+    #[test]
+    fn completes_when_woken_from_another_thread() {
+        let (simulated, future) = Simulated::start(3);
+
+        let output = block_on_timeout(future, Duration::from_secs(10));
+
+        assert_eq!(output.unwrap().unwrap(), Answer(42));
+        assert_eq!(simulated.polls.load(Ordering::Relaxed), 4);
+        assert_eq!(simulated.cancellations.load(Ordering::Relaxed), 0);
+        assert_eq!(simulated.freed.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn waits_for_the_continuation_when_polled_again() {
+        let (simulated, future) = Simulated::start(1);
+        let mut future = Box::pin(future);
+
+        let mut cx = Context::from_waker(Waker::noop());
+        for _ in 0..3 {
+            assert!(future.as_mut().poll(&mut cx).is_pending());
+        }
+        assert_eq!(simulated.polls.load(Ordering::Relaxed), 1);
+
+        let output = block_on_timeout(future, Duration::from_secs(10));
+        assert_eq!(output.unwrap().unwrap(), Answer(42));
+        assert_eq!(simulated.polls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn survives_being_moved_between_polls() {
+        let (simulated, future) = Simulated::start(1);
+        let mut future = Box::pin(future);
+
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+
+        // The continuation may still be running on the simulated SDK thread while it's moved.
+        let moved = *Pin::into_inner(future);
+        let output = block_on_timeout(moved, Duration::from_secs(10));
+
+        assert_eq!(output.unwrap().unwrap(), Answer(42));
+        assert_eq!(simulated.freed.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn stays_pending_once_finished() {
+        let (simulated, future) = Simulated::start(0);
+        let mut future = Box::pin(future);
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let output = loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                break output;
+            }
+            thread::yield_now();
+        };
+        assert_eq!(output.unwrap(), Answer(42));
+
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(simulated.completions.load(Ordering::Relaxed), 1);
+
+        drop(future);
+        assert_eq!(simulated.cancellations.load(Ordering::Relaxed), 0);
+        assert_eq!(simulated.freed.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn cancels_unfinished_futures_on_drop() {
+        let (simulated, future) = Simulated::start(usize::MAX);
+
+        assert!(block_on_timeout(future, Duration::from_millis(20)).is_none());
+
+        assert_eq!(simulated.completions.load(Ordering::Relaxed), 0);
+        assert_eq!(simulated.cancellations.load(Ordering::Relaxed), 1);
+        assert_eq!(simulated.freed.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,14 +1,33 @@
+#[cfg(feature = "sync")]
+use core::time::Duration;
+
 use crate::{
     errors::{CallStatus, ErrorTypeConverter, FfiResult},
     util::rust_call,
 };
 
 mod buffer;
+#[cfg_attr(
+    not(test),
+    expect(
+        dead_code,
+        unused_macros,
+        unused_imports,
+        reason = "no SDK function takes a callback interface yet"
+    )
+)]
+mod callbacks;
 mod errors;
 mod futures;
 mod util;
+mod waker;
 
-pub use {buffer::RustBuffer, errors::Error, util::validate_checksums, util::version};
+pub use {
+    buffer::RustBuffer,
+    errors::{Error, ErrorKind},
+    util::validate_checksums,
+    util::version,
+};
 
 #[cfg_attr(windows, link(name = "op_uniffi_core", kind = "raw-dylib"))]
 #[cfg_attr(not(windows), link(name = "op_uniffi_core"))]
@@ -17,6 +36,8 @@ unsafe extern "C" {
     unsafe fn uniffi_init_client(buffer: RustBuffer) -> futures::FfiFutureHandle<RustBuffer>;
     #[link_name = "uniffi_op_uniffi_core_fn_func_release_client"]
     unsafe fn uniffi_release_client(buffer: RustBuffer, status: *mut CallStatus);
+    #[link_name = "uniffi_op_uniffi_core_fn_func_invoke"]
+    unsafe fn uniffi_invoke(buffer: RustBuffer) -> futures::FfiFutureHandle<RustBuffer>;
 }
 
 #[cfg(feature = "sync")]
@@ -28,33 +49,31 @@ unsafe extern "C" {
 }
 
 #[cfg(feature = "async")]
-#[cfg_attr(windows, link(name = "op_uniffi_core", kind = "raw-dylib"))]
-#[cfg_attr(not(windows), link(name = "op_uniffi_core"))]
-unsafe extern "C" {
-    #[link_name = "uniffi_op_uniffi_core_fn_func_invoke"]
-    unsafe fn uniffi_invoke(buffer: RustBuffer) -> futures::FfiFutureHandle<RustBuffer>;
-}
-
-#[cfg(feature = "async")]
-pub async fn invoke(payload: &str) -> Result<RustBuffer, Error> {
-    let buffer: RustBuffer = payload.into();
-
-    let result = unsafe { uniffi_invoke(buffer) }
+pub async fn invoke(payload: RustBuffer) -> Result<RustBuffer, Error> {
+    let result = unsafe { uniffi_invoke(payload) }
         .into_future::<ErrorTypeConverter>()
         .await?;
 
     Ok(result)
 }
 
+/// Makes the invocation, blocking the thread until it finishes or `timeout` passes.
+///
+/// Calls with a timeout go through the SDK's async entry point instead, so they can be
+/// cancelled when they take too long.
 #[cfg(feature = "sync")]
-pub fn invoke_sync(payload: &str) -> Result<RustBuffer, Error> {
-    let buffer: RustBuffer = payload.into();
+pub fn invoke_sync(payload: RustBuffer, timeout: Option<Duration>) -> Result<RustBuffer, Error> {
+    let Some(timeout) = timeout else {
+        return unsafe { rust_call!(uniffi_invoke_sync -> ErrorTypeConverter, payload) };
+    };
 
-    unsafe { rust_call!(uniffi_invoke_sync -> ErrorTypeConverter, buffer) }
+    let future = unsafe { uniffi_invoke(payload) }.into_future::<ErrorTypeConverter>();
+    util::block_on_timeout(future, timeout).unwrap_or(Err(Error::Timeout { after: timeout }))
 }
 
+/// Creates a client from the serialized config, which the SDK takes ownership of its copy of.
 #[cfg(feature = "async")]
-pub async fn get_client_id_buffer(client_config: &str) -> FfiResult<RustBuffer> {
+pub async fn get_client_id_buffer(client_config: &[u8]) -> FfiResult<RustBuffer> {
     let buffer: RustBuffer = client_config.into();
 
     unsafe { uniffi_init_client(buffer) }
@@ -63,15 +82,21 @@ pub async fn get_client_id_buffer(client_config: &str) -> FfiResult<RustBuffer>
 }
 
 #[cfg(feature = "sync")]
-pub fn get_client_id_buffer_sync(client_config: &str) -> FfiResult<RustBuffer> {
+pub fn get_client_id_buffer_sync(
+    client_config: &[u8],
+    timeout: Option<Duration>,
+) -> FfiResult<RustBuffer> {
     let buffer: RustBuffer = client_config.into();
+    let future = unsafe { uniffi_init_client(buffer) }.into_future::<ErrorTypeConverter>();
 
-    pollster::block_on(unsafe { uniffi_init_client(buffer) }.into_future::<ErrorTypeConverter>())
+    match timeout {
+        Some(timeout) => util::block_on_timeout(future, timeout)
+            .unwrap_or(Err(Error::Timeout { after: timeout })),
+        None => pollster::block_on(future),
+    }
 }
 
-pub fn free_client(client_id: &str) {
+pub fn free_client(client_id: &str) -> FfiResult<()> {
     let buffer = RustBuffer::from(client_id);
-    match unsafe { rust_call!(uniffi_release_client, buffer) } {
-        Ok(()) => {}
-    }
+    unsafe { rust_call!(uniffi_release_client -> ErrorTypeConverter, buffer) }
 }
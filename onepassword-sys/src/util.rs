@@ -68,3 +68,62 @@ pub fn validate_checksums() {
 pub fn version() -> u32 {
     uniffi_contract_version()
 }
+
+/// Like `pollster::block_on`, but gives up and drops `future` once `timeout` has passed.
+#[cfg(feature = "sync")]
+pub(crate) fn block_on_timeout<F: Future>(
+    future: F,
+    timeout: std::time::Duration,
+) -> Option<F::Output> {
+    use std::{
+        sync::Arc,
+        task::{Context, Poll, Wake, Waker},
+        thread::{self, Thread},
+        time::Instant,
+    };
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let deadline = Instant::now() + timeout;
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = core::pin::pin!(future);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return Some(output);
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return None;
+        }
+
+        thread::park_timeout(deadline - now);
+    }
+}
+
+#[cfg(all(test, feature = "sync"))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn block_on_timeout_gives_up() {
+        let start = Instant::now();
+        let output = block_on_timeout(core::future::pending::<()>(), Duration::from_millis(50));
+
+        assert_eq!(output, None);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(
+            block_on_timeout(async { 1 }, Duration::from_millis(50)),
+            Some(1)
+        );
+    }
+}
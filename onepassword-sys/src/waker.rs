@@ -0,0 +1,163 @@
+//! An `AtomicWaker`, like the one in `futures`, so the SDK can wake a future from its own threads
+//! while the future is being polled with a different waker.
+
+use core::task::Waker;
+
+#[cfg(loom)]
+use loom::{cell::UnsafeCell, sync::atomic::AtomicU8};
+
+#[cfg(not(loom))]
+use core::sync::atomic::AtomicU8;
+
+use core::sync::atomic::Ordering::{AcqRel, Acquire, Release};
+
+/// Nobody's touching the waker.
+const IDLE: u8 = 0;
+/// [`AtomicWaker::register`] is replacing the waker.
+const REGISTERING: u8 = 0b01;
+/// [`AtomicWaker::wake`] is taking the waker, or wants to but found it being replaced.
+const WAKING: u8 = 0b10;
+
+pub(crate) struct AtomicWaker {
+    state: AtomicU8,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+// SAFETY: The waker is only accessed by whoever moved the state out of `IDLE`, see the methods.
+unsafe impl Send for AtomicWaker {}
+unsafe impl Sync for AtomicWaker {}
+
+impl AtomicWaker {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: AtomicU8::new(IDLE),
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    /// Replaces the waker to notify on [`AtomicWaker::wake`].
+    ///
+    /// Only one thread may register at a time, which is guaranteed by only calling it from
+    /// [`Future::poll`]. If a wake races with this, `waker` is woken right away instead of stored.
+    pub(crate) fn register(&self, waker: &Waker) {
+        match self
+            .state
+            .compare_exchange(IDLE, REGISTERING, Acquire, Acquire)
+        {
+            Ok(_) => {
+                // SAFETY: Being in `REGISTERING` gives exclusive access to the waker.
+                self.waker.with_mut(|slot| unsafe {
+                    match &mut *slot {
+                        Some(old) => old.clone_from(waker),
+                        slot => *slot = Some(waker.clone()),
+                    }
+                });
+
+                if let Err(state) = self
+                    .state
+                    .compare_exchange(REGISTERING, IDLE, AcqRel, Acquire)
+                {
+                    // A wake came in while registering and left the waker to us.
+                    debug_assert_eq!(state, REGISTERING | WAKING);
+
+                    // SAFETY: `wake` doesn't touch the waker while `REGISTERING` is set.
+                    let waker = self.waker.with_mut(|slot| unsafe { (*slot).take() });
+                    self.state.swap(IDLE, AcqRel);
+
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                }
+            }
+            // A wake is in progress, and might've missed the new waker.
+            Err(WAKING) => waker.wake_by_ref(),
+            // Another registration, which `Future::poll` taking `&mut self` rules out.
+            Err(_) => {}
+        }
+    }
+
+    /// Wakes the registered waker, if any.
+    pub(crate) fn wake(&self) {
+        if let Some(waker) = self.take() {
+            waker.wake();
+        }
+    }
+
+    fn take(&self) -> Option<Waker> {
+        match self.state.fetch_or(WAKING, AcqRel) {
+            IDLE => {
+                // SAFETY: Setting `WAKING` from `IDLE` gives exclusive access to the waker.
+                let waker = self.waker.with_mut(|slot| unsafe { (*slot).take() });
+                self.state.fetch_and(!WAKING, Release);
+                waker
+            }
+            // Either another wake is on it, or `register` will see `WAKING` and wake itself.
+            _ => None,
+        }
+    }
+}
+
+/// Mirrors loom's `UnsafeCell` API so the same code can be model checked.
+#[cfg(not(loom))]
+struct UnsafeCell<T>(core::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+    const fn new(value: T) -> Self {
+        Self(core::cell::UnsafeCell::new(value))
+    }
+
+    fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
+
+/// Run with `RUSTFLAGS="--cfg loom" cargo test -p onepassword-sys --lib waker`.
+#[cfg(all(test, loom))]
+mod tests {
+    use super::*;
+    use loom::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        thread,
+    };
+    use std::task::Wake;
+
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: std::sync::Arc<Self>) {
+            self.0.store(true, Ordering::Release);
+        }
+    }
+
+    /// Whichever order the SDK's wake and a re-registration happen in, the most recently
+    /// registered waker must be woken, or the future would hang.
+    #[test]
+    fn wakes_the_latest_waker() {
+        loom::model(|| {
+            let atomic = Arc::new(AtomicWaker::new());
+            let first = std::sync::Arc::new(Flag(AtomicBool::new(false)));
+            let second = std::sync::Arc::new(Flag(AtomicBool::new(false)));
+
+            atomic.register(&Waker::from(first.clone()));
+
+            let waking = thread::spawn({
+                let atomic = atomic.clone();
+                move || atomic.wake()
+            });
+            atomic.register(&Waker::from(second.clone()));
+            waking.join().unwrap();
+
+            let woken = second.0.load(Ordering::Acquire);
+            // If the wake got the first waker, the second one is still registered.
+            if !woken {
+                assert!(first.0.load(Ordering::Acquire));
+                atomic.wake();
+                assert!(second.0.load(Ordering::Acquire));
+            }
+        });
+    }
+}
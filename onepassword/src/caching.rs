@@ -0,0 +1,44 @@
+//! The caching client, see `onepassword_shared::define_caching!`.
+
+onepassword_shared::define_caching!(sync);
+
+mod refresher {
+    use crate::Client;
+    use onepassword_shared::cache::SecretCache;
+    use std::{
+        sync::{Arc, mpsc},
+        thread,
+    };
+
+    /// Refreshes secrets on a thread, which exits once every clone of the caching client is
+    /// dropped. There's no thread unless refreshing ahead is configured.
+    pub(super) struct Refresher(Option<mpsc::Sender<String>>);
+
+    impl Refresher {
+        pub(super) fn new(client: &Client, cache: &Arc<SecretCache>) -> Self {
+            if cache.config().refresh_ahead.is_none() {
+                return Self(None);
+            }
+
+            let (sender, receiver) = mpsc::channel::<String>();
+            let (client, cache) = (client.clone(), Arc::clone(cache));
+
+            thread::spawn(move || {
+                for reference in receiver {
+                    match client.resolve(&reference) {
+                        Ok(secret) => cache.refreshed(&reference, secret),
+                        Err(_) => cache.refresh_failed(&reference),
+                    }
+                }
+            });
+
+            Self(Some(sender))
+        }
+
+        pub(super) fn queue(&self, reference: &str) -> bool {
+            self.0
+                .as_ref()
+                .is_some_and(|sender| sender.send(reference.to_owned()).is_ok())
+        }
+    }
+}
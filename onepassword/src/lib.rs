@@ -1,17 +1,25 @@
-use onepassword_shared::types::{Invocation, InvocationWrapper};
-
+pub mod caching;
+pub mod registry;
 pub mod wrappers;
 
-pub use onepassword_shared::types::ClientConfig;
-pub use onepassword_sys::{Error as FfiError, version};
+pub use caching::CachingClient;
+#[cfg(feature = "metrics")]
+pub use onepassword_shared::metrics::MetricsFacade;
+pub use onepassword_shared::{
+    cache::CacheConfig,
+    config::ClientConfigBuilder,
+    errors::{ConfigError, InitError, LookupError, RegistryError},
+    metrics::{CallRecord, MetricsSink},
+    registry::RegistryConfig,
+    response::Response,
+    retry::RetryPolicy,
+    types::{Auth, ClientConfig, ItemView, VaultView, WebsiteView},
+};
+pub use onepassword_sys::{Error as FfiError, ErrorKind, version};
+pub use registry::ClientRegistry;
 pub use wrappers::Client;
 
-pub fn invoke<T: serde::de::DeserializeOwned>(invocation: Invocation) -> Result<T, FfiError> {
-    let serialized_config = serde_json::to_string(&InvocationWrapper { invocation }).unwrap();
-    let result = onepassword_sys::invoke_sync(&serialized_config)?;
-    let value = serde_json::from_reader(result.as_ref()).unwrap();
-    Ok(value)
-}
+onepassword_shared::define_invoke!(sync);
 
 #[cfg(test)]
 mod tests {
@@ -23,9 +31,9 @@ mod tests {
     #[test]
     fn connect() {
         let vaults = Client::new(ClientConfig {
-            service_account_token: ONEPASS_SERVICE_ACCOUNT_TOKEN.to_owned(),
-            integration_name: env!("CARGO_PKG_NAME"),
-            integration_version: env!("CARGO_PKG_VERSION"),
+            auth: Auth::ServiceAccount(ONEPASS_SERVICE_ACCOUNT_TOKEN.into()),
+            integration_name: env!("CARGO_PKG_NAME").into(),
+            integration_version: env!("CARGO_PKG_VERSION").into(),
             ..Default::default()
         })
         .unwrap()